@@ -0,0 +1,219 @@
+use std::fmt;
+
+/// One column of a pairwise alignment. The first sequence is treated as the reference:
+/// an insertion is an element only present in the second sequence, a deletion one only
+/// present in the first.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Column<T> {
+	Match(T, T),
+	Mismatch(T, T),
+	Insertion(T),
+	Deletion(T),
+}
+
+impl<T> Column<T> where T: Copy {
+	pub fn first(&self) -> Option<T> {
+		match *self {
+			Column::Match(a, _) | Column::Mismatch(a, _) | Column::Deletion(a) => Some(a),
+			Column::Insertion(_) => None,
+		}
+	}
+
+	pub fn second(&self) -> Option<T> {
+		match *self {
+			Column::Match(_, b) | Column::Mismatch(_, b) | Column::Insertion(b) => Some(b),
+			Column::Deletion(_) => None,
+		}
+	}
+
+	pub fn is_gap(&self) -> bool {
+		matches!(*self, Column::Insertion(_) | Column::Deletion(_))
+	}
+}
+
+/// A pairwise alignment together with its score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alignment<T> {
+	pub score: i64,
+	pub columns: Vec<Column<T>>,
+}
+
+impl<T> Alignment<T> where T: Eq + Copy {
+	/// Build the columns from two gapped sequences of equal length, as produced by a traceback.
+	/// Positions where both sides are gaps are dropped.
+	pub fn from_pair(seq1: Vec<Option<T>>, seq2: Vec<Option<T>>, score: i64) -> Alignment<T> {
+		assert_eq!(seq1.len(), seq2.len(), "Aligned sequences must have the same length!");
+		let columns = seq1.into_iter().zip(seq2).filter_map(|pair| match pair {
+			(Some(a), Some(b)) => Some(if a == b { Column::Match(a, b) } else { Column::Mismatch(a, b) }),
+			(Some(a), None) => Some(Column::Deletion(a)),
+			(None, Some(b)) => Some(Column::Insertion(b)),
+			(None, None) => None,
+		}).collect();
+		Alignment { score, columns }
+	}
+
+	/// The first sequence with None for gaps.
+	pub fn seq1(&self) -> Vec<Option<T>> {
+		self.columns.iter().map(|c| c.first()).collect()
+	}
+
+	/// The second sequence with None for gaps.
+	pub fn seq2(&self) -> Vec<Option<T>> {
+		self.columns.iter().map(|c| c.second()).collect()
+	}
+
+	pub fn to_pair(&self) -> (Vec<Option<T>>, Vec<Option<T>>) {
+		(self.seq1(), self.seq2())
+	}
+
+	pub fn len(&self) -> usize {
+		self.columns.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.columns.is_empty()
+	}
+
+	fn count(&self, pred: &dyn Fn(&Column<T>) -> bool) -> usize {
+		self.columns.iter().filter(|c| pred(c)).count()
+	}
+
+	pub fn matches(&self) -> usize {
+		self.count(&|c| matches!(*c, Column::Match(..)))
+	}
+
+	pub fn mismatches(&self) -> usize {
+		self.count(&|c| matches!(*c, Column::Mismatch(..)))
+	}
+
+	pub fn insertions(&self) -> usize {
+		self.count(&|c| matches!(*c, Column::Insertion(_)))
+	}
+
+	pub fn deletions(&self) -> usize {
+		self.count(&|c| matches!(*c, Column::Deletion(_)))
+	}
+
+	/// Number of gap columns, i.e. insertions plus deletions.
+	pub fn gaps(&self) -> usize {
+		self.count(&|c| c.is_gap())
+	}
+
+	/// Number of gaps counted as runs: consecutive insertions (or deletions) form one gap.
+	pub fn gap_opens(&self) -> usize {
+		let mut res = 0;
+		let mut prev: Option<&Column<T>> = None;
+		for c in &self.columns {
+			let same_kind = matches!((prev, c), (Some(&Column::Insertion(_)), &Column::Insertion(_)) |
+			                                    (Some(&Column::Deletion(_)), &Column::Deletion(_)));
+			if c.is_gap() && !same_kind {
+				res += 1;
+			}
+			prev = Some(c);
+		}
+		res
+	}
+
+	/// Fraction of columns that are matches, 0 for an empty alignment.
+	pub fn identity(&self) -> f64 {
+		if self.is_empty() {
+			0.0
+		} else {
+			self.matches() as f64 / self.len() as f64
+		}
+	}
+
+	/// Score of the columns under affine gap penalties: a gap run of length k scores
+	/// gap_open + (k - 1) * gap_extend.
+	pub fn affine_score(&self, similarity: &dyn Fn(&T, &T) -> i64, gap_open: i64, gap_extend: i64) -> i64 {
+		let mut score = 0;
+		let mut prev: Option<&Column<T>> = None;
+		for c in &self.columns {
+			score += match (prev, c) {
+				(_, &Column::Match(ref a, ref b)) | (_, &Column::Mismatch(ref a, ref b)) => similarity(a, b),
+				(Some(&Column::Insertion(_)), &Column::Insertion(_)) => gap_extend,
+				(Some(&Column::Deletion(_)), &Column::Deletion(_)) => gap_extend,
+				_ => gap_open,
+			};
+			prev = Some(c);
+		}
+		score
+	}
+
+	/// Score of the columns under a linear gap penalty.
+	pub fn linear_score(&self, similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> i64 {
+		self.affine_score(similarity, gap_penalty, gap_penalty)
+	}
+}
+
+/// The classic three-line view: the first sequence, a line with `|` under every match, and
+/// the second sequence, with `-` for gaps. Columns are padded to the widest element, except
+/// for the last one, so that the lines end with the last elements as they display.
+impl<T> fmt::Display for Alignment<T> where T: fmt::Display + Eq + Copy {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut lines = [String::new(), String::new(), String::new()];
+		for (i, c) in self.columns.iter().enumerate() {
+			let a = c.first().map_or("-".to_string(), |x| x.to_string());
+			let b = c.second().map_or("-".to_string(), |x| x.to_string());
+			let width = a.chars().count().max(b.chars().count());
+			let padded = if i + 1 == self.columns.len() { 0 } else { width };
+			let marker = if let Column::Match(..) = *c { "|" } else { " " };
+			lines[0].push_str(&format!("{:<w$}", a, w = padded));
+			lines[1].push_str(&marker.repeat(width));
+			lines[2].push_str(&format!("{:<w$}", b, w = padded));
+		}
+		writeln!(f, "{}", lines[0])?;
+		// the markers are only bars and spaces, trailing spaces mark nothing
+		writeln!(f, "{}", lines[1].trim_end())?;
+		write!(f, "{}", lines[2])
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Alignment, Column};
+
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 1 } else { -1 }
+	}
+
+	#[test]
+	fn test_alignment() {
+		let seq1 = vec![Some('w'), Some('h'), Some('a'), Some('t'), None, None, Some('x')];
+		let seq2 = vec![Some('w'), Some('h'), Some('i'), Some('t'), Some('e'), Some('s'), None];
+		let a = Alignment::from_pair(seq1.clone(), seq2.clone(), -2);
+		assert_eq!(a.columns[0], Column::Match('w', 'w'));
+		assert_eq!(a.columns[2], Column::Mismatch('a', 'i'));
+		assert_eq!(a.columns[4], Column::Insertion('e'));
+		assert_eq!(a.columns[6], Column::Deletion('x'));
+		assert_eq!(a.to_pair(), (seq1, seq2));
+		assert_eq!(a.len(), 7);
+		assert_eq!((a.matches(), a.mismatches(), a.insertions(), a.deletions()), (3, 1, 2, 1));
+		assert_eq!(a.gaps(), 3);
+		assert_eq!(a.gap_opens(), 2);
+		assert_eq!(a.identity(), 3.0 / 7.0);
+		assert_eq!(a.linear_score(&similarity, -1), 3 - 1 - 3);
+		assert_eq!(a.affine_score(&similarity, -3, -1), 3 - 1 - 3 - 1 - 3);
+		assert_eq!(format!("{}", a), "what--x\n|| |\nwhites-");
+		let empty: Alignment<char> = Alignment::from_pair(vec![], vec![], 0);
+		assert_eq!(empty.identity(), 0.0);
+		assert_eq!(format!("{}", empty), "\n\n");
+	}
+
+	#[test]
+	fn test_alignment_display_wide() {
+		let a = Alignment::from_pair(vec![Some(10), Some(2), None], vec![Some(10), Some(300), Some(4)], 0);
+		assert_eq!(format!("{}", a), "102  -\n||\n103004");
+	}
+
+	#[test]
+	fn test_alignment_display_whitespace() {
+		// elements that display as whitespace stay part of the lines, like the columns
+		let a = Alignment::from_pair(vec![Some('a'), Some(' '), Some(' ')], vec![Some('a'), Some(' '), None], 0);
+		assert_eq!(format!("{}", a), "a  \n||\na -");
+		let a = Alignment::from_pair(vec![Some('a'), None], vec![Some('a'), Some(' ')], 0);
+		assert_eq!(format!("{}", a), "a-\n|\na ");
+		let a = Alignment::from_pair(vec![Some(1), Some(300)], vec![Some(10), Some(300)], 0);
+		assert_eq!(format!("{}", a), "1 300\n  |||\n10300");
+	}
+}
@@ -0,0 +1,289 @@
+//! Banded global alignment: only cells with |i - j| <= k are computed, O((n + m) * k) time
+//! and memory instead of O(n * m). Meant for sequences known to differ by few edits.
+use needleman_wunsch::{Direction, ResultEntry};
+use alignment::Alignment;
+
+// Score of cells outside the band, far enough from i64::MIN that adding penalties cannot overflow.
+const NEG_INF: i64 = i64::MIN / 4;
+
+// Distinct elements of each sequence up to which `align_banded` scores every pair of them to
+// bound the score outside the band.
+const MAX_DISTINCT: usize = 256;
+
+/// Result of a banded alignment. `band` is the k that was used, `optimal` tells whether the
+/// alignment is guaranteed to be an optimal global alignment. If it is false, a better one
+/// may leave the band.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BandedAlignment<T> {
+	pub alignment: Alignment<T>,
+	pub band: usize,
+	pub optimal: bool,
+}
+
+pub trait Banded<T> {
+	/// Align within the band |i - j| <= k. The band is widened to the length difference of the
+	/// sequences if it is narrower, otherwise no global alignment would fit, and narrowed to
+	/// the longer length if it is wider, which already covers the whole matrix. Optimality is
+	/// checked against the best score of any pair of elements of the two sequences; with more
+	/// than 256 distinct elements in either of them only a band covering the whole matrix
+	/// counts as optimal.
+	fn align_banded(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, k: usize) -> BandedAlignment<T>;
+	/// Like `align_banded`, with the best score of a pair given by the caller, who must make
+	/// sure that no pair of elements scores more than `max_similarity`.
+	fn align_banded_bounded(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, k: usize, max_similarity: i64) -> BandedAlignment<T>;
+	/// Start with band k and double it until the result is guaranteed optimal.
+	fn align_banded_adaptive(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, k: usize) -> BandedAlignment<T> {
+		let mut k = k;
+		loop {
+			let res = self.align_banded(vec, similarity, gap_penalty, k);
+			if res.optimal {
+				return res;
+			}
+			k = res.band.max(1) * 2;
+		}
+	}
+}
+
+impl<T> Banded<T> for [T] where T: Eq + Copy + Clone {
+	fn align_banded(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, k: usize) -> BandedAlignment<T> {
+		banded(self, vec, similarity, gap_penalty, k, max_pair_similarity(self, vec, similarity))
+	}
+
+	fn align_banded_bounded(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, k: usize, max_similarity: i64) -> BandedAlignment<T> {
+		banded(self, vec, similarity, gap_penalty, k, Some(max_similarity))
+	}
+}
+
+/// The banded alignment, optimal if its score reaches the bound outside the band or, without
+/// a bound on the pairs, if the band covers the whole matrix.
+fn banded<T>(vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, k: usize, max_similarity: Option<i64>) -> BandedAlignment<T>
+	where T: Eq + Copy {
+	let (n, m) = (vec1.len(), vec2.len());
+	let k = k.min(n.max(m)).max(n.abs_diff(m));
+	let band = fill_band(vec1, vec2, similarity, gap_penalty, k);
+	let (seq1, seq2) = band_traceback(&band, vec1, vec2, k);
+	let score = band[n][m + k - n].score;
+	let bound = max_similarity.map_or(NEG_INF, |s| outside_band_bound(n, m, k, gap_penalty, s));
+	BandedAlignment {
+		alignment: Alignment::from_pair(seq1, seq2, score),
+		band: k,
+		optimal: score >= bound && (max_similarity.is_some() || covers_matrix(n, m, k)),
+	}
+}
+
+/// The distinct elements of a sequence, None if there are more than `MAX_DISTINCT`.
+fn distinct<T: Eq>(vec: &[T]) -> Option<Vec<&T>> {
+	let mut res: Vec<&T> = Vec::new();
+	for a in vec {
+		if !res.contains(&a) {
+			if res.len() == MAX_DISTINCT {
+				return None;
+			}
+			res.push(a);
+		}
+	}
+	Some(res)
+}
+
+/// Best score of an element of the first sequence with one of the second, None if there are
+/// too many distinct elements to score every pair. Without pairs there is nothing to bound.
+fn max_pair_similarity<T: Eq>(vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64) -> Option<i64> {
+	let (distinct1, distinct2) = (distinct(vec1)?, distinct(vec2)?);
+	Some(distinct1.iter().flat_map(|&a| distinct2.iter().map(move |&b| similarity(a, b))).max().unwrap_or(0))
+}
+
+/// Whether every cell of the matrix is within the band.
+fn covers_matrix(n: usize, m: usize, k: usize) -> bool {
+	2 * (k + 1) - n.abs_diff(m) > n + m
+}
+
+/// Upper bound of the score of any alignment through a cell outside the band. Reaching the
+/// diagonal k + 1 and coming back to the diagonal m - n takes at least 2(k + 1) - |n - m| gaps,
+/// the remaining elements form pairs scoring at most `max_similarity` each. The score is linear
+/// in the number of gaps, so the bound is the larger of the fewest and the most gaps possible.
+fn outside_band_bound(n: usize, m: usize, k: usize, gap_penalty: i64, max_similarity: i64) -> i64 {
+	if covers_matrix(n, m, k) {
+		return NEG_INF;
+	}
+	let gaps = 2 * (k + 1) - n.abs_diff(m);
+	let fewest = gaps as i64 * gap_penalty + ((n + m - gaps) / 2) as i64 * max_similarity;
+	let most = (n + m) as i64 * gap_penalty;
+	fewest.max(most)
+}
+
+/// Row i holds the cells j = i - k ..= i + k at index j + k - i, cells outside the matrix or
+/// the band score NEG_INF. Ties are broken like the default of NeedlemanWunsch: Match, Up, Left.
+fn fill_band<T>(vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, k: usize) -> Vec<Vec<ResultEntry>> {
+	let width = 2 * k + 1;
+	let mut band = vec![vec![ResultEntry::new(NEG_INF, Direction::Undefined); width]; vec1.len() + 1];
+	band[0][k] = ResultEntry::new(0, Direction::Undefined);
+	for i in 0..(vec1.len() + 1) {
+		let first = i.saturating_sub(k);
+		let last = (i + k).min(vec2.len());
+		for j in first..(last + 1) {
+			if i == 0 && j == 0 {
+				continue;
+			}
+			let idx = j + k - i;
+			let mut best = ResultEntry::new(NEG_INF, Direction::Undefined);
+			if i > 0 && j > 0 {
+				let score = band[i-1][idx].score + similarity(&vec1[i-1], &vec2[j-1]);
+				best = ResultEntry::new(score, Direction::Match);
+			}
+			// the cell above is in the band unless j is at its right edge
+			if i > 0 && idx + 1 < width {
+				let score = band[i-1][idx + 1].score + gap_penalty;
+				if score > best.score {
+					best = ResultEntry::new(score, Direction::Up);
+				}
+			}
+			if j > 0 && idx > 0 {
+				let score = band[i][idx - 1].score + gap_penalty;
+				if score > best.score {
+					best = ResultEntry::new(score, Direction::Left);
+				}
+			}
+			band[i][idx] = best;
+		}
+	}
+	band
+}
+
+fn band_traceback<T>(band: &[Vec<ResultEntry>], vec1: &[T], vec2: &[T], k: usize) -> (Vec<Option<T>>, Vec<Option<T>>)
+	where T: Copy {
+	let mut seq1 = Vec::new();
+	let mut seq2 = Vec::new();
+	let (mut i, mut j) = (vec1.len(), vec2.len());
+	loop {
+		match band[i][j + k - i].direction {
+			Direction::Match => {
+				seq1.push(Some(vec1[i-1]));
+				seq2.push(Some(vec2[j-1]));
+				i -= 1;
+				j -= 1;
+			},
+			Direction::Up => {
+				seq1.push(Some(vec1[i-1]));
+				seq2.push(None);
+				i -= 1;
+			},
+			Direction::Left => {
+				seq1.push(None);
+				seq2.push(Some(vec2[j-1]));
+				j -= 1;
+			},
+			Direction::Undefined => break,
+		}
+	}
+	seq1.reverse();
+	seq2.reverse();
+	(seq1, seq2)
+}
+
+#[cfg(test)]
+mod test {
+	use super::Banded;
+	use needleman_wunsch::{NeedlemanWunsch, align_score};
+	use test_support::random_dna;
+
+	// a few substitutions and indels spread over the sequence
+	fn mutate(seq: &[u8]) -> Vec<u8> {
+		let mut res = seq.to_vec();
+		res[10] = if res[10] == b'A' { b'C' } else { b'A' };
+		res.remove(40);
+		res.insert(70, b'G');
+		res.insert(71, b'T');
+		res.remove(120);
+		res
+	}
+
+	fn similarity(a: &u8, b: &u8) -> i64 {
+		if a == b { 2 } else { -1 }
+	}
+
+	#[test]
+	fn test_banded_matches_full() {
+		let vec1 = random_dna(150, 3);
+		let vec2 = mutate(&vec1);
+		let res = vec1.align_banded(&vec2, &similarity, -2, 8);
+		assert!(res.optimal);
+		let full = vec1.align(&vec2, &similarity, -2);
+		assert_eq!(res.alignment, full);
+		assert_eq!(res.alignment.linear_score(&similarity, -2), res.alignment.score);
+	}
+
+	#[test]
+	fn test_banded_narrow() {
+		let vec1 = b"AAAAGGGG".to_vec();
+		let vec2 = b"GGGGAAAA".to_vec();
+		// the band is widened to the length difference
+		let res = vec1.align_banded(&b"GG"[..], &similarity, -1, 0);
+		assert_eq!(res.band, 6);
+		assert!(res.alignment.seq2().iter().filter(|c| c.is_some()).count() == 2);
+		// with k = 0 only the diagonal is allowed, which is far from optimal here
+		let res = vec1.align_banded(&vec2, &similarity, -1, 0);
+		assert_eq!(res.band, 0);
+		assert_eq!(res.alignment.score, -8);
+		assert!(!res.optimal);
+		let res = vec1.align_banded_adaptive(&vec2, &similarity, -1, 0);
+		assert!(res.optimal);
+		assert_eq!(res.alignment.score, align_score(&vec1, &vec2, &similarity, -1));
+	}
+
+	#[test]
+	fn test_banded_adaptive_unrelated() {
+		for seed in 0..5 {
+			let vec1 = random_dna(60 + seed as usize * 7, seed);
+			let vec2 = random_dna(50, seed + 100);
+			let res = vec1.align_banded_adaptive(&vec2, &similarity, -2, 1);
+			assert!(res.optimal);
+			assert_eq!(res.alignment.score, align_score(&vec1, &vec2, &similarity, -2));
+			assert_eq!(res.alignment.linear_score(&similarity, -2), res.alignment.score);
+		}
+		let empty: Vec<u8> = Vec::new();
+		let res = empty.align_banded(&b"ACG"[..], &similarity, -1, 0);
+		assert!(res.optimal);
+		assert_eq!(res.alignment.score, -3);
+	}
+
+	#[test]
+	fn test_banded_different_pair_scores_most() {
+		// A against B scores more than any element with itself: the best alignment pairs the
+		// last A with the first B far outside a narrow band
+		let similarity = |a: &u8, b: &u8| match (*a, *b) {
+			(b'A', b'B') => 10,
+			(a, b) if a == b => 1,
+			_ => -1,
+		};
+		let (vec1, vec2) = (b"CCCA".to_vec(), b"BCCC".to_vec());
+		let res = vec1.align_banded(&vec2, &similarity, -1, 1);
+		assert_eq!(res.alignment.score, 1);
+		assert!(!res.optimal);
+		let res = vec1.align_banded_adaptive(&vec2, &similarity, -1, 1);
+		assert!(res.optimal);
+		assert_eq!(res.alignment.score, 4);
+		assert_eq!(res.alignment.score, align_score(&vec1, &vec2, &similarity, -1));
+		// too many distinct elements to score every pair: optimal only when the band covers
+		// the whole matrix
+		let vec1: Vec<u32> = (0..300).collect();
+		let res = vec1.align_banded(&vec1, &|a, b| if a == b { 1 } else { -1 }, -1, 10);
+		assert!(!res.optimal);
+		assert_eq!(res.alignment.score, 300);
+		assert!(vec1.align_banded(&vec1, &|a, b| if a == b { 1 } else { -1 }, -1, 300).optimal);
+		assert!(vec1.align_banded_bounded(&vec1, &|a, b| if a == b { 1 } else { -1 }, -1, 10, 1).optimal);
+	}
+
+	#[test]
+	fn test_banded_wide() {
+		// a band wider than the matrix is narrowed to it instead of overflowing the width
+		let vec1 = random_dna(40, 7);
+		let vec2 = random_dna(30, 8);
+		let res = vec1.align_banded(&vec2, &similarity, -2, usize::MAX);
+		assert_eq!(res.band, 40);
+		assert!(res.optimal);
+		assert_eq!(res.alignment, vec1.align(&vec2, &similarity, -2));
+		let res = vec1.align_banded_adaptive(&vec2, &similarity, -2, usize::MAX / 2 + 1);
+		assert!(res.optimal);
+		assert_eq!(res.band, 40);
+	}
+}
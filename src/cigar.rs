@@ -0,0 +1,400 @@
+//! CIGAR strings and minimal SAM records. The first sequence of an alignment is the
+//! reference and the second one the query (read), so an `Insertion` column, which only has an
+//! element of the second sequence, is a CIGAR `I` and a `Deletion` column a `D`.
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use alignment::{Alignment, Column};
+use smith_waterman::LocalAlignment;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CigarError {
+	// byte offset in the CIGAR string and a description of the problem
+	Parse(usize, String),
+	// the operations do not fit the sequences they are applied to
+	Length(String),
+}
+
+impl fmt::Display for CigarError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CigarError::Parse(offset, ref msg) => write!(f, "offset {}: {}", offset, msg),
+			CigarError::Length(ref msg) => write!(f, "CIGAR does not fit the sequences: {}", msg),
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CigarOp {
+	/// `M`: a pair of elements, equal or not.
+	Match,
+	/// `I`: an element only in the query.
+	Insertion,
+	/// `D`: an element only in the reference.
+	Deletion,
+	/// `S`: query elements at the ends that are not aligned but part of the SAM sequence.
+	SoftClip,
+	/// `H`: query elements at the ends that are left out entirely.
+	HardClip,
+	/// `=`: a pair of equal elements.
+	Equal,
+	/// `X`: a pair of different elements.
+	Diff,
+}
+
+impl CigarOp {
+	pub fn to_char(self) -> char {
+		match self {
+			CigarOp::Match => 'M',
+			CigarOp::Insertion => 'I',
+			CigarOp::Deletion => 'D',
+			CigarOp::SoftClip => 'S',
+			CigarOp::HardClip => 'H',
+			CigarOp::Equal => '=',
+			CigarOp::Diff => 'X',
+		}
+	}
+
+	pub fn from_char(c: char) -> Option<CigarOp> {
+		match c {
+			'M' => Some(CigarOp::Match),
+			'I' => Some(CigarOp::Insertion),
+			'D' => Some(CigarOp::Deletion),
+			'S' => Some(CigarOp::SoftClip),
+			'H' => Some(CigarOp::HardClip),
+			'=' => Some(CigarOp::Equal),
+			'X' => Some(CigarOp::Diff),
+			_ => None,
+		}
+	}
+
+	fn consumes_reference(self) -> bool {
+		matches!(self, CigarOp::Match | CigarOp::Deletion | CigarOp::Equal | CigarOp::Diff)
+	}
+
+	fn consumes_query(self) -> bool {
+		matches!(self, CigarOp::Match | CigarOp::Insertion | CigarOp::SoftClip | CigarOp::Equal | CigarOp::Diff)
+	}
+}
+
+/// Run-length encoded operations, e.g. `3M1I2D`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cigar(pub Vec<(usize, CigarOp)>);
+
+impl Cigar {
+	/// The CIGAR of an alignment. With `extended` pairs are written as `=` and `X`, otherwise
+	/// both are `M`.
+	pub fn from_alignment<T>(alignment: &Alignment<T>, extended: bool) -> Cigar {
+		let mut cigar = Cigar::default();
+		for c in &alignment.columns {
+			cigar.push(match *c {
+				Column::Match(..) if extended => CigarOp::Equal,
+				Column::Mismatch(..) if extended => CigarOp::Diff,
+				Column::Match(..) | Column::Mismatch(..) => CigarOp::Match,
+				Column::Insertion(_) => CigarOp::Insertion,
+				Column::Deletion(_) => CigarOp::Deletion,
+			}, 1);
+		}
+		cigar
+	}
+
+	/// Append `n` times `op`, merging with the last run if it is the same operation.
+	pub fn push(&mut self, op: CigarOp, n: usize) {
+		if n == 0 {
+			return;
+		}
+		match self.0.last_mut() {
+			Some(&mut (ref mut len, last)) if last == op => *len += n,
+			_ => self.0.push((n, op)),
+		}
+	}
+
+	/// Number of reference elements the operations cover.
+	pub fn reference_len(&self) -> usize {
+		self.0.iter().filter(|&&(_, op)| op.consumes_reference()).map(|&(n, _)| n).sum()
+	}
+
+	/// Number of query elements the operations cover, including soft clipped ones.
+	pub fn query_len(&self) -> usize {
+		self.0.iter().filter(|&&(_, op)| op.consumes_query()).map(|&(n, _)| n).sum()
+	}
+
+	/// Rebuild the alignment of `reference` and `query` the operations describe. Clipped query
+	/// elements are not part of it. The score is 0, rescore the result with `linear_score` or
+	/// `affine_score`.
+	pub fn to_alignment<T>(&self, reference: &[T], query: &[T]) -> Result<Alignment<T>, CigarError>
+		where T: Eq + Copy {
+		if self.reference_len() != reference.len() {
+			return Err(CigarError::Length(format!("covers {} reference elements, not {}", self.reference_len(), reference.len())));
+		}
+		if self.query_len() != query.len() {
+			return Err(CigarError::Length(format!("covers {} query elements, not {}", self.query_len(), query.len())));
+		}
+		let (mut i, mut j) = (0, 0);
+		let (mut seq1, mut seq2) = (Vec::new(), Vec::new());
+		for &(n, op) in &self.0 {
+			for _ in 0..n {
+				match op {
+					CigarOp::Match | CigarOp::Equal | CigarOp::Diff => {
+						if (op == CigarOp::Equal && reference[i] != query[j]) || (op == CigarOp::Diff && reference[i] == query[j]) {
+							return Err(CigarError::Length(format!("{} does not fit reference position {}", op.to_char(), i)));
+						}
+						seq1.push(Some(reference[i]));
+						seq2.push(Some(query[j]));
+					},
+					CigarOp::Insertion => {
+						seq1.push(None);
+						seq2.push(Some(query[j]));
+					},
+					CigarOp::Deletion => {
+						seq1.push(Some(reference[i]));
+						seq2.push(None);
+					},
+					CigarOp::SoftClip | CigarOp::HardClip => {},
+				}
+				if op.consumes_reference() {
+					i += 1;
+				}
+				if op.consumes_query() {
+					j += 1;
+				}
+			}
+		}
+		Ok(Alignment::from_pair(seq1, seq2, 0))
+	}
+}
+
+impl fmt::Display for Cigar {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.0.is_empty() {
+			// SAM writes a missing CIGAR as `*`
+			return write!(f, "*");
+		}
+		for &(n, op) in &self.0 {
+			write!(f, "{}{}", n, op.to_char())?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for Cigar {
+	type Err = CigarError;
+
+	fn from_str(s: &str) -> Result<Cigar, CigarError> {
+		let mut cigar = Cigar::default();
+		if s == "*" {
+			return Ok(cigar);
+		}
+		// offset of the length of every operation, for the errors of the clip positions
+		let mut offsets = Vec::new();
+		let mut start = 0;
+		for (offset, c) in s.char_indices() {
+			if c.is_ascii_digit() {
+				continue;
+			}
+			let op = CigarOp::from_char(c).ok_or_else(|| CigarError::Parse(offset, format!("unknown operation '{}'", c)))?;
+			if start == offset {
+				return Err(CigarError::Parse(offset, format!("'{}' without a length", c)));
+			}
+			let n = s[start..offset].parse().map_err(|e| CigarError::Parse(start, format!("{}", e)))?;
+			if n == 0 {
+				return Err(CigarError::Parse(start, format!("'{}' of length zero", c)));
+			}
+			cigar.0.push((n, op));
+			offsets.push(start);
+			start = offset + c.len_utf8();
+		}
+		if start != s.len() {
+			return Err(CigarError::Parse(start, "length without an operation".to_string()));
+		}
+		// hard clips only at the ends, soft clips only between them and the aligned part
+		let only_hard_clips = |ops: &[(usize, CigarOp)]| ops.iter().all(|&(_, op)| op == CigarOp::HardClip);
+		for (i, &(_, op)) in cigar.0.iter().enumerate() {
+			if op == CigarOp::HardClip && i != 0 && i != cigar.0.len() - 1 {
+				return Err(CigarError::Parse(offsets[i], "hard clip between other operations".to_string()));
+			}
+			if op == CigarOp::SoftClip && !only_hard_clips(&cigar.0[..i]) && !only_hard_clips(&cigar.0[i + 1..]) {
+				return Err(CigarError::Parse(offsets[i], "soft clip between aligned operations".to_string()));
+			}
+		}
+		Ok(cigar)
+	}
+}
+
+/// The mandatory fields of a SAM line for a mapped read on the forward strand, plus the
+/// alignment score as `AS:i`. `pos` is 1-based like in SAM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamRecord {
+	pub qname: String,
+	pub flag: u16,
+	pub rname: String,
+	pub pos: usize,
+	pub mapq: u8,
+	pub cigar: Cigar,
+	pub seq: Vec<u8>,
+	pub qual: Option<Vec<u8>>,
+	pub score: i64,
+}
+
+impl SamRecord {
+	/// Record of a global or semi-global alignment of the read (second sequence) to the
+	/// reference (first sequence). Reference elements before the first and after the last
+	/// aligned read element only move the position and are not part of the CIGAR.
+	pub fn from_alignment(qname: &str, rname: &str, alignment: &Alignment<u8>) -> SamRecord {
+		let is_read = |c: &Column<u8>| c.second().is_some();
+		let first = alignment.columns.iter().position(&is_read).unwrap_or(0);
+		let last = alignment.columns.iter().rposition(&is_read).map_or(0, |l| l + 1);
+		let trimmed = Alignment { score: alignment.score, columns: alignment.columns[first..last.max(first)].to_vec() };
+		SamRecord {
+			qname: qname.to_string(),
+			flag: 0,
+			rname: rname.to_string(),
+			pos: first + 1,
+			mapq: 255,
+			cigar: Cigar::from_alignment(&trimmed, false),
+			seq: alignment.seq2().into_iter().flatten().collect(),
+			qual: None,
+			score: alignment.score,
+		}
+	}
+
+	/// Record of a local alignment of the whole read, the parts of the read outside the local
+	/// alignment are soft clipped. The read must be the second sequence the local alignment
+	/// was computed on.
+	pub fn from_local(qname: &str, rname: &str, local: &LocalAlignment<u8>, read: &[u8]) -> Result<SamRecord, CigarError> {
+		let aligned: Vec<u8> = local.alignment.seq2().into_iter().flatten().collect();
+		if local.end.1 > read.len() || read[local.start.1..local.end.1] != aligned[..] {
+			return Err(CigarError::Length(format!("the read of length {} does not have the aligned elements at {}..{}", read.len(), local.start.1, local.end.1)));
+		}
+		let mut cigar = Cigar::default();
+		cigar.push(CigarOp::SoftClip, local.start.1);
+		for &(n, op) in &Cigar::from_alignment(&local.alignment, false).0 {
+			cigar.push(op, n);
+		}
+		cigar.push(CigarOp::SoftClip, read.len() - local.end.1);
+		Ok(SamRecord {
+			qname: qname.to_string(),
+			flag: 0,
+			rname: rname.to_string(),
+			pos: local.start.0 + 1,
+			mapq: 255,
+			cigar,
+			seq: read.to_vec(),
+			qual: None,
+			score: local.alignment.score,
+		})
+	}
+}
+
+impl fmt::Display for SamRecord {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let field = |v: &[u8]| if v.is_empty() { "*".to_string() } else { String::from_utf8_lossy(v).into_owned() };
+		write!(f, "{}\t{}\t{}\t{}\t{}\t{}\t*\t0\t0\t{}\t{}\tAS:i:{}",
+			self.qname, self.flag, self.rname, self.pos, self.mapq, self.cigar,
+			field(&self.seq), field(self.qual.as_ref().map_or(&[][..], |q| &q[..])), self.score)
+	}
+}
+
+/// Write a SAM file: a header with the given reference names and lengths, then one line per record.
+pub fn write_sam<W: Write>(writer: &mut W, references: &[(&str, usize)], records: &[SamRecord]) -> io::Result<()> {
+	writeln!(writer, "@HD\tVN:1.6\tSO:unsorted")?;
+	for &(name, len) in references {
+		writeln!(writer, "@SQ\tSN:{}\tLN:{}", name, len)?;
+	}
+	for record in records {
+		writeln!(writer, "{}", record)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Cigar, CigarError, CigarOp, SamRecord, write_sam};
+	use alignment::Alignment;
+	use needleman_wunsch::{AlignMode, NeedlemanWunsch};
+	use smith_waterman::SmithWaterman;
+
+	fn similarity(a: &u8, b: &u8) -> i64 {
+		if a == b { 2 } else { -1 }
+	}
+
+	fn pair(s1: &str, s2: &str) -> Alignment<u8> {
+		let gapped = |s: &str| s.bytes().map(|c| if c == b'-' { None } else { Some(c) }).collect();
+		Alignment::from_pair(gapped(s1), gapped(s2), 0)
+	}
+
+	#[test]
+	fn test_from_alignment() {
+		let a = pair("ACGT--ACCA", "ACTTGGA-CA");
+		assert_eq!(Cigar::from_alignment(&a, false).to_string(), "4M2I1M1D2M");
+		assert_eq!(Cigar::from_alignment(&a, true).to_string(), "2=1X1=2I1=1D2=");
+		let cigar = Cigar::from_alignment(&a, true);
+		assert_eq!(cigar.reference_len(), 8);
+		assert_eq!(cigar.query_len(), 9);
+		let empty: Alignment<u8> = Alignment::from_pair(vec![], vec![], 0);
+		assert_eq!(Cigar::from_alignment(&empty, false).to_string(), "*");
+	}
+
+	#[test]
+	fn test_parse_round_trip() {
+		let vec1 = b"GATTACAGATTACA".to_vec();
+		let vec2 = b"GATCAGGATTTACA".to_vec();
+		let a = vec1.align(&vec2, &similarity, -2);
+		for &extended in &[false, true] {
+			let text = Cigar::from_alignment(&a, extended).to_string();
+			let cigar: Cigar = text.parse().unwrap();
+			assert_eq!(cigar.to_string(), text);
+			let back = cigar.to_alignment(&vec1, &vec2).unwrap();
+			assert_eq!(back.to_pair(), a.to_pair());
+			assert_eq!(back.linear_score(&similarity, -2), a.score);
+		}
+		let cigar: Cigar = "2S3M1H".parse().unwrap();
+		assert_eq!(cigar.0, vec![(2, CigarOp::SoftClip), (3, CigarOp::Match), (1, CigarOp::HardClip)]);
+		assert_eq!(cigar.to_alignment(&b"ACG"[..], &b"TTACG"[..]).unwrap().to_pair().1, vec![Some(b'A'), Some(b'C'), Some(b'G')]);
+		assert_eq!("*".parse::<Cigar>(), Ok(Cigar::default()));
+		assert_eq!("3M2".parse::<Cigar>(), Err(CigarError::Parse(2, "length without an operation".to_string())));
+		assert!(matches!("3M2Q".parse::<Cigar>(), Err(CigarError::Parse(3, _))));
+		assert!(matches!("M".parse::<Cigar>(), Err(CigarError::Parse(0, _))));
+		assert!(matches!("0M".parse::<Cigar>(), Err(CigarError::Parse(0, _))));
+		assert!(matches!("3M00I2M".parse::<Cigar>(), Err(CigarError::Parse(2, _))));
+		// clips only at the ends, hard clips outside soft clips
+		assert!("1H2S3M1I3M2S1H".parse::<Cigar>().is_ok());
+		assert!("2S3M".parse::<Cigar>().is_ok());
+		assert!(matches!("2M1S2M".parse::<Cigar>(), Err(CigarError::Parse(2, _))));
+		assert!(matches!("2M1H2M".parse::<Cigar>(), Err(CigarError::Parse(2, _))));
+		assert!(matches!("2S1H3M".parse::<Cigar>(), Err(CigarError::Parse(2, _))));
+		assert!(matches!("3M1H2S".parse::<Cigar>(), Err(CigarError::Parse(2, _))));
+		assert!(matches!(cigar.to_alignment(&b"AC"[..], &b"TTACG"[..]), Err(CigarError::Length(_))));
+		let mismatch: Cigar = "1=".parse().unwrap();
+		assert!(mismatch.to_alignment(&b"A"[..], &b"C"[..]).is_err());
+	}
+
+	#[test]
+	fn test_sam() {
+		let reference = b"TTTTGATTACATTTT".to_vec();
+		let read = b"GATCACA".to_vec();
+		let glocal = reference.align_mode(&read, &similarity, -2, AlignMode::glocal());
+		let record = SamRecord::from_alignment("read1", "chr1", &glocal);
+		assert_eq!(record.pos, 5);
+		assert_eq!(record.cigar.reference_len() + 4 + 4, reference.len());
+		assert_eq!(record.to_string(), format!("read1\t0\tchr1\t5\t255\t{}\t*\t0\t0\tGATCACA\t*\tAS:i:{}", record.cigar, glocal.score));
+
+		let read = b"CCCGATTACACCC".to_vec();
+		let local = reference.local_align(&read, &similarity, -2);
+		let record = SamRecord::from_local("read2", "chr1", &local, &read).unwrap();
+		assert_eq!(record.cigar.to_string(), "3S7M3S");
+		assert_eq!(record.pos, 5);
+		assert_eq!(record.cigar.query_len(), read.len());
+
+		let mut out = Vec::new();
+		write_sam(&mut out, &[("chr1", reference.len())], &[record]).unwrap();
+		let text = String::from_utf8(out).unwrap();
+		let lines: Vec<_> = text.lines().collect();
+		assert_eq!(lines[1], "@SQ\tSN:chr1\tLN:15");
+		assert_eq!(lines[2], "read2\t0\tchr1\t5\t255\t3S7M3S\t*\t0\t0\tCCCGATTACACCC\t*\tAS:i:14");
+		// a read the local alignment was not computed on
+		assert!(matches!(SamRecord::from_local("read2", "chr1", &local, &read[..8]), Err(CigarError::Length(_))));
+		assert!(matches!(SamRecord::from_local("read2", "chr1", &local, b"CCCGATTTCACCC"), Err(CigarError::Length(_))));
+	}
+}
@@ -0,0 +1,114 @@
+use std::fmt::Debug;
+use std::ops::{Add, Sub, Mul, Neg};
+
+use definite_num::DefinitelyANumber;
+use rational::Rational;
+
+/// A number type that can be used as a coordinate of a `Point`.
+/// Products of two coordinates (dot and cross products, determinants) are computed in the
+/// `Wide` type, which for the integer types is large enough that orientation tests are exact:
+/// for any i32 coordinates, and for i64 coordinates of absolute value at most 2^62.
+pub trait Coordinate: Copy + Ord + Debug + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self> {
+    type Wide: Copy + PartialOrd + Debug + Add<Output = Self::Wide> + Sub<Output = Self::Wide> + Mul<Output = Self::Wide>;
+
+    fn zero() -> Self;
+    fn to_wide(self) -> Self::Wide;
+    fn wide_zero() -> Self::Wide;
+    fn to_f64(self) -> f64;
+    fn wide_to_f64(w: Self::Wide) -> f64;
+    // the point halfway between a and b, rounded towards negative infinity for integers
+    fn midway(self, other: Self) -> Self;
+}
+
+impl Coordinate for DefinitelyANumber {
+    type Wide = f64;
+    fn zero() -> DefinitelyANumber {
+        DefinitelyANumber::new(0.0).unwrap()
+    }
+    fn to_wide(self) -> f64 {
+        self.to_f64()
+    }
+    fn wide_zero() -> f64 {
+        0.0
+    }
+    fn to_f64(self) -> f64 {
+        DefinitelyANumber::to_f64(&self)
+    }
+    fn wide_to_f64(w: f64) -> f64 {
+        w
+    }
+    fn midway(self, other: DefinitelyANumber) -> DefinitelyANumber {
+        DefinitelyANumber::new((self.to_f64() + other.to_f64()) / 2.0).unwrap()
+    }
+}
+
+macro_rules! integer_coordinate {
+    ($t: ty, $wide: ty) => {
+        impl Coordinate for $t {
+            type Wide = $wide;
+            fn zero() -> $t {
+                0
+            }
+            fn to_wide(self) -> $wide {
+                self as $wide
+            }
+            fn wide_zero() -> $wide {
+                0
+            }
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+            fn wide_to_f64(w: $wide) -> f64 {
+                w as f64
+            }
+            fn midway(self, other: $t) -> $t {
+                ((self as $wide + other as $wide).div_euclid(2)) as $t
+            }
+        }
+    }
+}
+
+integer_coordinate!(i32, i128);
+integer_coordinate!(i64, i128);
+
+impl Coordinate for Rational {
+    type Wide = Rational;
+    fn zero() -> Rational {
+        Rational::from_integer(0)
+    }
+    fn to_wide(self) -> Rational {
+        self
+    }
+    fn wide_zero() -> Rational {
+        Rational::from_integer(0)
+    }
+    fn to_f64(self) -> f64 {
+        Rational::to_f64(&self)
+    }
+    fn wide_to_f64(w: Rational) -> f64 {
+        w.to_f64()
+    }
+    fn midway(self, other: Rational) -> Rational {
+        (self + other) * Rational::new(1, 2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Coordinate;
+    use definite_num::DefinitelyANumber;
+    use rational::Rational;
+
+    #[test]
+    fn test_coordinate() {
+        assert_eq!(i32::MAX.to_wide() * i32::MIN.to_wide(), (i32::MAX as i128) * (i32::MIN as i128));
+        assert_eq!(i64::MAX.to_wide() * i64::MAX.to_wide(), (i64::MAX as i128).pow(2));
+        assert_eq!(3i32.midway(6), 4);
+        assert_eq!((-3i64).midway(0), -2);
+        assert_eq!(Rational::from_integer(1).midway(Rational::from_integer(2)), Rational::new(3, 2));
+        let a = DefinitelyANumber::new(1.0).unwrap();
+        let b = DefinitelyANumber::new(2.0).unwrap();
+        assert_eq!(a.midway(b), 1.5);
+        assert_eq!(<DefinitelyANumber as Coordinate>::zero(), 0.0);
+    }
+}
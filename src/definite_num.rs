@@ -1,5 +1,5 @@
 use std::cmp::Ordering;
-use std::ops::{Add, Sub, Mul};
+use std::ops::{Add, Sub, Mul, Neg};
 
 #[derive(Debug, Copy, Clone)]
 pub struct DefinitelyANumber(f64);
@@ -40,6 +40,13 @@ impl Mul for DefinitelyANumber {
     }
 }
 
+impl Neg for DefinitelyANumber {
+    type Output = DefinitelyANumber;
+    fn neg(self) -> DefinitelyANumber {
+        DefinitelyANumber(-self.0)
+    }
+}
+
 impl PartialEq for DefinitelyANumber {
     fn eq(&self, other: &DefinitelyANumber) -> bool {
         self.0 == other.0
@@ -0,0 +1,530 @@
+//! Unified diff of two texts by line. The lines are aligned as sequences with a score of 1
+//! for equal lines, -1 for a substitution and 0 for a gap, so the optimal alignment keeps a
+//! longest common subsequence of lines and never pairs different lines. The quadratic
+//! alignment only runs on small pieces: like patience diff, lines occurring exactly once in
+//! both texts are matched first (the longest chain of them in the same order), together with
+//! the common prefix and suffix of every piece, and only the gaps between these anchors are
+//! aligned, in linear space with Hirschberg's algorithm. A gap too large for that is aligned
+//! within a band around the diagonal, which is exact when its two sides differ in few lines,
+//! and only shown as removed and added as a whole when no affordable band is. Changed lines
+//! can be refined by aligning their words the same way.
+use std::collections::HashMap;
+use std::fmt;
+
+use alignment::Column;
+use banded::Banded;
+use hirschberg::Hirschberg;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+	Context(&'a str),
+	Removed(&'a str),
+	Added(&'a str),
+}
+
+impl<'a> DiffLine<'a> {
+	fn text(&self) -> &'a str {
+		match *self {
+			DiffLine::Context(s) | DiffLine::Removed(s) | DiffLine::Added(s) => s,
+		}
+	}
+}
+
+/// Changed lines with the context around them. Starts are 0-based line indices, the header
+/// shows them 1-based. Lines keep their line terminator, the last line of a text may lack it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk<'a> {
+	pub old_start: usize,
+	pub old_len: usize,
+	pub new_start: usize,
+	pub new_len: usize,
+	pub lines: Vec<DiffLine<'a>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DiffOptions {
+	/// Unchanged lines shown before and after every change.
+	pub context: usize,
+	/// Show changed lines as one block with the removed and added words marked inline, like
+	/// `git diff --word-diff=plain`: `[-removed-]{+added+}`, lines without a prefix.
+	pub word_diff: bool,
+}
+
+impl Default for DiffOptions {
+	fn default() -> DiffOptions {
+		DiffOptions { context: 3, word_diff: false }
+	}
+}
+
+/// A hunk range: a single line is shown as its number, an empty range as the line before it.
+fn range(start: usize, len: usize) -> String {
+	match len {
+		0 => format!("{},0", start),
+		1 => format!("{}", start + 1),
+		_ => format!("{},{}", start + 1, len),
+	}
+}
+
+/// Line following a last line without line terminator.
+const NO_NEWLINE: &str = "\\ No newline at end of file\n";
+
+fn write_line(f: &mut fmt::Formatter, prefix: &str, line: &str) -> fmt::Result {
+	if line.ends_with('\n') {
+		write!(f, "{}{}", prefix, line)
+	} else {
+		write!(f, "{}{}\n{}", prefix, line, NO_NEWLINE)
+	}
+}
+
+/// Append a line and, if it has no line terminator, the marker line saying so.
+fn push_line(res: &mut String, line: &str) {
+	res.push_str(line);
+	if !line.ends_with('\n') {
+		res.push('\n');
+		res.push_str(NO_NEWLINE);
+	}
+}
+
+impl<'a> fmt::Display for Hunk<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "@@ -{} +{} @@", range(self.old_start, self.old_len), range(self.new_start, self.new_len))?;
+		for line in &self.lines {
+			let prefix = match *line {
+				DiffLine::Context(_) => " ",
+				DiffLine::Removed(_) => "-",
+				DiffLine::Added(_) => "+",
+			};
+			write_line(f, prefix, line.text())?;
+		}
+		Ok(())
+	}
+}
+
+/// Largest gap between anchors, in matrix cells, that is aligned optimally.
+const MAX_DP_CELLS: usize = 1 << 22;
+
+/// Positions (i, j) of the elements occurring exactly once in both sequences, as the longest
+/// chain increasing in both, found by patience sorting on j.
+fn unique_chain(seq1: &[&str], seq2: &[&str]) -> Vec<(usize, usize)> {
+	// occurrences and last position in the first and in the second sequence
+	let mut counts: HashMap<&str, (usize, usize, usize, usize)> = HashMap::new();
+	for (i, &x) in seq1.iter().enumerate() {
+		let c = counts.entry(x).or_insert((0, 0, 0, 0));
+		c.0 += 1;
+		c.1 = i;
+	}
+	for (j, &x) in seq2.iter().enumerate() {
+		if let Some(c) = counts.get_mut(x) {
+			c.2 += 1;
+			c.3 = j;
+		}
+	}
+	let mut pairs: Vec<(usize, usize)> = counts.values().filter(|c| c.0 == 1 && c.2 == 1).map(|c| (c.1, c.3)).collect();
+	pairs.sort();
+	// tails[l]: index in pairs of the smallest end of an increasing chain of length l + 1
+	let mut tails: Vec<usize> = Vec::new();
+	let mut prev: Vec<Option<usize>> = vec![None; pairs.len()];
+	for k in 0..pairs.len() {
+		let l = tails.partition_point(|&t| pairs[t].1 < pairs[k].1);
+		prev[k] = if l > 0 { Some(tails[l - 1]) } else { None };
+		if l == tails.len() {
+			tails.push(k);
+		} else {
+			tails[l] = k;
+		}
+	}
+	let mut chain = Vec::with_capacity(tails.len());
+	let mut k = tails.last().cloned();
+	while let Some(t) = k {
+		chain.push(pairs[t]);
+		k = prev[t];
+	}
+	chain.reverse();
+	chain
+}
+
+/// Matched pairs found without the quadratic alignment, sorted: the common prefix and suffix
+/// of a piece, then the unique chain of what is left, whose gaps are pieces again. Pieces
+/// without unique common elements are left for the alignment.
+fn anchors(seq1: &[&str], seq2: &[&str]) -> Vec<(usize, usize)> {
+	let mut res = Vec::new();
+	let mut pieces = vec![(0, seq1.len(), 0, seq2.len())];
+	while let Some((mut lo1, mut hi1, mut lo2, mut hi2)) = pieces.pop() {
+		while lo1 < hi1 && lo2 < hi2 && seq1[lo1] == seq2[lo2] {
+			res.push((lo1, lo2));
+			lo1 += 1;
+			lo2 += 1;
+		}
+		while lo1 < hi1 && lo2 < hi2 && seq1[hi1 - 1] == seq2[hi2 - 1] {
+			hi1 -= 1;
+			hi2 -= 1;
+			res.push((hi1, hi2));
+		}
+		let chain = unique_chain(&seq1[lo1..hi1], &seq2[lo2..hi2]);
+		if chain.is_empty() {
+			continue;
+		}
+		let (mut start1, mut start2) = (lo1, lo2);
+		for (i, j) in chain {
+			let (i, j) = (lo1 + i, lo2 + j);
+			pieces.push((start1, i, start2, j));
+			res.push((i, j));
+			start1 = i + 1;
+			start2 = j + 1;
+		}
+		pieces.push((start1, hi1, start2, hi2));
+	}
+	res.sort();
+	res
+}
+
+/// Columns of the gap between two anchors: a longest common subsequence alignment, or every
+/// element removed and added if the gap is too large.
+fn align_gap<'a>(seq1: &[&'a str], seq2: &[&'a str]) -> Vec<Column<&'a str>> {
+	let similarity = |a: &&str, b: &&str| if a == b { 1 } else { -1 };
+	if seq1.len().saturating_mul(seq2.len()) <= MAX_DP_CELLS {
+		return seq1.align_linear_space(seq2, &similarity, 0).columns;
+	}
+	// an optimal banded alignment has no mismatch either: replacing it by two gaps scores more.
+	// No pair of lines scores more than 1, there are too many of them to check
+	let rows = seq1.len() + 1;
+	let mut k = seq1.len().abs_diff(seq2.len()).max(16);
+	while (2 * k + 1).saturating_mul(rows) <= MAX_DP_CELLS {
+		let banded = seq1.align_banded_bounded(seq2, &similarity, 0, k, 1);
+		if banded.optimal {
+			return banded.alignment.columns;
+		}
+		k *= 2;
+	}
+	let removed = seq1.iter().map(|&a| Column::Deletion(a));
+	removed.chain(seq2.iter().map(|&b| Column::Insertion(b))).collect()
+}
+
+/// Columns of a common subsequence alignment of two sequences: the anchors as matches and
+/// the gaps between them aligned.
+fn align_lcs<'a>(seq1: &[&'a str], seq2: &[&'a str]) -> Vec<Column<&'a str>> {
+	let mut columns = Vec::with_capacity(seq1.len().max(seq2.len()));
+	let (mut i, mut j) = (0, 0);
+	for (ai, aj) in anchors(seq1, seq2).into_iter().chain(Some((seq1.len(), seq2.len()))) {
+		columns.extend(align_gap(&seq1[i..ai], &seq2[j..aj]));
+		if ai < seq1.len() {
+			columns.push(Column::Match(seq1[ai], seq2[aj]));
+		}
+		i = ai + 1;
+		j = aj + 1;
+	}
+	columns
+}
+
+/// Every line of both texts in diff order: the removed lines of a change before the added ones.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+	let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+	let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+	let mut res = Vec::with_capacity(old_lines.len().max(new_lines.len()));
+	let mut added = Vec::new();
+	for c in align_lcs(&old_lines, &new_lines) {
+		match c {
+			Column::Match(a, _) => {
+				res.append(&mut added);
+				res.push(DiffLine::Context(a));
+			},
+			Column::Deletion(a) => res.push(DiffLine::Removed(a)),
+			Column::Insertion(b) => added.push(DiffLine::Added(b)),
+			// the substitution score is below two gaps, see the module documentation
+			Column::Mismatch(..) => unreachable!(),
+		}
+	}
+	res.append(&mut added);
+	res
+}
+
+/// The changes between the texts as hunks with `context` unchanged lines around every change.
+/// Changes closer than twice the context share a hunk.
+pub fn hunks<'a>(old: &'a str, new: &'a str, context: usize) -> Vec<Hunk<'a>> {
+	let lines = diff_lines(old, new);
+	let changed: Vec<usize> = (0..lines.len()).filter(|&k| !matches!(lines[k], DiffLine::Context(_))).collect();
+	let mut res = Vec::new();
+	let mut k = 0;
+	while k < changed.len() {
+		let first = changed[k];
+		let mut last = first;
+		k += 1;
+		while k < changed.len() && changed[k] - last - 1 <= 2 * context {
+			last = changed[k];
+			k += 1;
+		}
+		let start = first.saturating_sub(context);
+		let end = (last + context + 1).min(lines.len());
+		let old_start = lines[..start].iter().filter(|l| !matches!(l, DiffLine::Added(_))).count();
+		let new_start = lines[..start].iter().filter(|l| !matches!(l, DiffLine::Removed(_))).count();
+		let hunk = &lines[start..end];
+		res.push(Hunk {
+			old_start,
+			old_len: hunk.iter().filter(|l| !matches!(l, DiffLine::Added(_))).count(),
+			new_start,
+			new_len: hunk.iter().filter(|l| !matches!(l, DiffLine::Removed(_))).count(),
+			lines: hunk.to_vec(),
+		});
+	}
+	res
+}
+
+/// Runs of whitespace and runs of everything else, which concatenate to the text again.
+fn words(s: &str) -> Vec<&str> {
+	let mut res = Vec::new();
+	let mut start = 0;
+	let mut prev: Option<bool> = None;
+	for (i, c) in s.char_indices() {
+		let space = c.is_whitespace();
+		if prev.is_some_and(|p| p != space) {
+			res.push(&s[start..i]);
+			start = i;
+		}
+		prev = Some(space);
+	}
+	if start < s.len() {
+		res.push(&s[start..]);
+	}
+	res
+}
+
+/// Append the text with every line of it between the markers, line breaks stay outside.
+fn mark(res: &mut String, text: &str, open: &str, close: &str) {
+	for (i, part) in text.split('\n').enumerate() {
+		if i > 0 {
+			res.push('\n');
+		}
+		if !part.is_empty() {
+			res.push_str(open);
+			res.push_str(part);
+			res.push_str(close);
+		}
+	}
+}
+
+/// The new text with the words only in the old one marked `[-...-]` and the words only in the
+/// new one marked `{+...+}`.
+pub fn word_diff(old: &str, new: &str) -> String {
+	fn flush(res: &mut String, removed: &mut String, added: &mut String) {
+		mark(res, removed, "[-", "-]");
+		mark(res, added, "{+", "+}");
+		removed.clear();
+		added.clear();
+	}
+	let mut res = String::new();
+	let (mut removed, mut added) = (String::new(), String::new());
+	for c in align_lcs(&words(old), &words(new)) {
+		match c {
+			Column::Match(a, _) => {
+				flush(&mut res, &mut removed, &mut added);
+				res.push_str(a);
+			},
+			Column::Deletion(a) => removed.push_str(a),
+			Column::Insertion(b) => added.push_str(b),
+			Column::Mismatch(..) => unreachable!(),
+		}
+	}
+	flush(&mut res, &mut removed, &mut added);
+	res
+}
+
+/// One hunk with every block of changed lines refined to a word diff. A missing line
+/// terminator is written as the marker line of the plain diff, so when only one side lacks it
+/// the marker words show up as removed or added.
+fn word_diff_hunk(hunk: &Hunk) -> String {
+	let mut res = format!("@@ -{} +{} @@\n", range(hunk.old_start, hunk.old_len), range(hunk.new_start, hunk.new_len));
+	let (mut removed, mut added) = (String::new(), String::new());
+	for line in hunk.lines.iter().map(Some).chain(Some(None)) {
+		match line {
+			Some(&DiffLine::Removed(s)) => push_line(&mut removed, s),
+			Some(&DiffLine::Added(s)) => push_line(&mut added, s),
+			_ => {
+				if !removed.is_empty() || !added.is_empty() {
+					res.push_str(&word_diff(&removed, &added));
+					removed.clear();
+					added.clear();
+				}
+				if let Some(&DiffLine::Context(s)) = line {
+					push_line(&mut res, s);
+				}
+			},
+		}
+	}
+	res
+}
+
+/// Unified diff of the texts under the given file names, empty if they are equal.
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str, options: DiffOptions) -> String {
+	let hunks = hunks(old, new, options.context);
+	if hunks.is_empty() {
+		return String::new();
+	}
+	let mut res = format!("--- {}\n+++ {}\n", old_name, new_name);
+	for hunk in &hunks {
+		if options.word_diff {
+			res.push_str(&word_diff_hunk(hunk));
+		} else {
+			res.push_str(&hunk.to_string());
+		}
+	}
+	res
+}
+
+#[cfg(test)]
+mod test {
+	use super::{DiffLine, DiffOptions, Hunk, anchors, hunks, unified_diff, word_diff};
+	use test_support::random_seq;
+
+	fn numbered(lines: &[usize]) -> String {
+		lines.iter().map(|i| format!("{}\n", i)).collect()
+	}
+
+	// apply the hunks to the old text, which must give the new one
+	fn patch(old: &str, hunks: &[Hunk]) -> String {
+		let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+		let mut res = String::new();
+		let mut next = 0;
+		for hunk in hunks {
+			for line in &old_lines[next..hunk.old_start] {
+				res.push_str(line);
+			}
+			next = hunk.old_start;
+			for line in &hunk.lines {
+				match *line {
+					DiffLine::Context(s) => {
+						assert_eq!(s, old_lines[next]);
+						res.push_str(s);
+						next += 1;
+					},
+					DiffLine::Removed(s) => {
+						assert_eq!(s, old_lines[next]);
+						next += 1;
+					},
+					DiffLine::Added(s) => res.push_str(s),
+				}
+			}
+		}
+		for line in &old_lines[next..] {
+			res.push_str(line);
+		}
+		res
+	}
+
+	fn random_lines(n: usize, seed: u64) -> String {
+		random_seq(&["foo\n", "bar\n", "baz\n", "qux\n", "\n"], n, seed).concat()
+	}
+
+	#[test]
+	fn test_unified_diff() {
+		let old = numbered(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+		let new = numbered(&[1, 2, 3, 4, 50, 55, 6, 7, 8, 9, 10]);
+		assert_eq!(unified_diff("a.txt", "b.txt", &old, &new, DiffOptions::default()), [
+			"--- a.txt",
+			"+++ b.txt",
+			"@@ -2,7 +2,8 @@",
+			" 2",
+			" 3",
+			" 4",
+			"-5",
+			"+50",
+			"+55",
+			" 6",
+			" 7",
+			" 8",
+			"",
+		].join("\n"));
+		assert_eq!(unified_diff("a", "b", &old, &old, DiffOptions::default()), "");
+		let zero = DiffOptions { context: 0, word_diff: false };
+		assert_eq!(unified_diff("a", "b", "", "x\ny\n", zero), "--- a\n+++ b\n@@ -0,0 +1,2 @@\n+x\n+y\n");
+		assert_eq!(unified_diff("a", "b", "x\ny\n", "x\n", zero), "--- a\n+++ b\n@@ -2 +1,0 @@\n-y\n");
+		assert_eq!(unified_diff("a", "b", "x\ny", "x\ny\n", zero), "--- a\n+++ b\n@@ -2 +2 @@\n-y\n\\ No newline at end of file\n+y\n");
+	}
+
+	#[test]
+	fn test_hunks_context() {
+		let old = numbered(&(1..21).collect::<Vec<_>>());
+		let mut changed: Vec<usize> = (1..21).collect();
+		changed[2] = 300;
+		changed[12] = 1300;
+		let new = numbered(&changed);
+		// nine unchanged lines between the changes
+		assert_eq!(hunks(&old, &new, 3).len(), 2);
+		assert_eq!(hunks(&old, &new, 4).len(), 2);
+		assert_eq!(hunks(&old, &new, 5).len(), 1);
+		let two = hunks(&old, &new, 1);
+		assert_eq!((two[0].old_start, two[0].old_len, two[0].new_start, two[0].new_len), (1, 3, 1, 3));
+		assert_eq!((two[1].old_start, two[1].old_len), (11, 3));
+		assert_eq!(two[1].lines, vec![DiffLine::Context("12\n"), DiffLine::Removed("13\n"), DiffLine::Added("1300\n"), DiffLine::Context("14\n")]);
+	}
+
+	#[test]
+	fn test_patch_round_trip() {
+		for seed in 0..20 {
+			let old = random_lines(30 + seed as usize, seed);
+			let new = random_lines(25, seed + 100);
+			for &context in &[0, 1, 3] {
+				let hunks = hunks(&old, &new, context);
+				assert_eq!(patch(&old, &hunks), new, "seed {}, context {}", seed, context);
+			}
+		}
+	}
+
+	#[test]
+	fn test_large_files() {
+		// 20000 lines with scattered edits: only the few lines around every edit are left
+		// between the anchors for the quadratic alignment
+		let old: Vec<usize> = (0..20_000).collect();
+		let mut new = old.clone();
+		for k in 0..21 {
+			match k % 3 {
+				0 => new[k * 900 + 5] = 100_000 + k,
+				1 => new.insert(k * 900 + 5, 200_000 + k),
+				_ => { new.remove(k * 900 + 5); },
+			}
+		}
+		let (old, new) = (numbered(&old), numbered(&new));
+		let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+		let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+		let anchors = anchors(&old_lines, &new_lines);
+		let mut prev = (0, 0);
+		for &(i, j) in anchors.iter().chain(Some(&(old_lines.len(), new_lines.len()))) {
+			assert!((i - prev.0) * (j - prev.1) <= 1, "gap {:?} to {:?}", prev, (i, j));
+			prev = (i + 1, j + 1);
+		}
+		let hunks = hunks(&old, &new, 3);
+		assert_eq!(hunks.len(), 21);
+		assert_eq!(patch(&old, &hunks), new);
+	}
+
+	#[test]
+	fn test_large_repetitive_files() {
+		// no line is unique, the single large gap is aligned in a band
+		let old = random_lines(20_000, 7);
+		let mut new_lines: Vec<&str> = old.split_inclusive('\n').collect();
+		for k in 0..21 {
+			new_lines[k * 950 + 3] = "changed\n";
+		}
+		let new: String = new_lines.concat();
+		let hunks = hunks(&old, &new, 0);
+		assert!(hunks.len() <= 21);
+		assert!(hunks.iter().all(|h| h.old_len <= 1 && h.new_len <= 1), "{:?}", hunks.iter().map(|h| (h.old_len, h.new_len)).collect::<Vec<_>>());
+		assert_eq!(patch(&old, &hunks), new);
+	}
+
+	#[test]
+	fn test_word_diff() {
+		assert_eq!(word_diff("the quick brown fox\n", "the slow brown fox\n"), "the [-quick-]{+slow+} brown fox\n");
+		assert_eq!(word_diff("a b", "a b c"), "a b{+ c+}");
+		assert_eq!(word_diff("x\n", "x\nnew line\n"), "x\n{+new line+}\n");
+		let old = "one\ntwo three\nfour\n";
+		let new = "one\ntwo 3\nfour\n";
+		let options = DiffOptions { context: 1, word_diff: true };
+		assert_eq!(unified_diff("a", "b", old, new, options), "--- a\n+++ b\n@@ -1,3 +1,3 @@\none\ntwo [-three-]{+3+}\nfour\n");
+		// the only change to the last line is its terminator
+		let options = DiffOptions { context: 0, word_diff: true };
+		assert_eq!(unified_diff("a", "b", "a\nb\nc", "a\nB\nc\n", options),
+			"--- a\n+++ b\n@@ -2,2 +2,2 @@\n[-b-]{+B+}\nc\n[-\\ No newline at end of file-]\n");
+		assert_eq!(unified_diff("a", "b", "x\ny", "x\nz", options),
+			"--- a\n+++ b\n@@ -2 +2 @@\n[-y-]{+z+}\n\\ No newline at end of file\n");
+	}
+}
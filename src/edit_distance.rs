@@ -0,0 +1,190 @@
+//! Distances between sequences without building the alignment: Levenshtein, optimal string
+//! alignment (Damerau with adjacent transpositions, no substring edited twice), longest
+//! common subsequence and Hamming. The `_within` variants stop as soon as the distance is
+//! known to exceed the threshold and return None then.
+use needleman_wunsch::{align_score, NeedlemanWunsch};
+use alignment::Column;
+
+fn unit_cost<T: Eq>(a: &T, b: &T) -> i64 {
+	if a == b { 0 } else { -1 }
+}
+
+fn unit_match<T: Eq>(a: &T, b: &T) -> i64 {
+	if a == b { 1 } else { 0 }
+}
+
+/// Number of insertions, deletions and substitutions turning `a` into `b`: the negated
+/// score of a global alignment where every edit scores -1.
+pub fn levenshtein<T: Eq>(a: &[T], b: &[T]) -> usize {
+	(-align_score(a, b, &unit_cost, -1)) as usize
+}
+
+/// Levenshtein distance if it is at most `max`. Every row of the matrix only grows from its
+/// minimum on, so once a whole row exceeds `max` so does the result.
+pub fn levenshtein_within<T: Eq>(a: &[T], b: &[T], max: usize) -> Option<usize> {
+	if a.len().abs_diff(b.len()) > max {
+		return None;
+	}
+	let mut prev: Vec<usize> = (0..(b.len() + 1)).collect();
+	let mut cur = vec![0; b.len() + 1];
+	for (i, x) in a.iter().enumerate() {
+		cur[0] = i + 1;
+		for (j, y) in b.iter().enumerate() {
+			let substitution = prev[j] + if x == y { 0 } else { 1 };
+			cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+		}
+		if *cur.iter().min().unwrap() > max {
+			return None;
+		}
+		::std::mem::swap(&mut prev, &mut cur);
+	}
+	Some(prev[b.len()]).filter(|&d| d <= max)
+}
+
+/// Optimal string alignment distance: Levenshtein plus transpositions of two adjacent
+/// elements, where no element is edited again after being transposed.
+pub fn osa_distance<T: Eq>(a: &[T], b: &[T]) -> usize {
+	osa_within(a, b, usize::MAX).unwrap()
+}
+
+/// OSA distance if it is at most `max`. A transposition reaches back two rows, so a row above
+/// `max` alone does not end the computation, two such rows in a row do.
+pub fn osa_within<T: Eq>(a: &[T], b: &[T], max: usize) -> Option<usize> {
+	if a.len().abs_diff(b.len()) > max {
+		return None;
+	}
+	let mut before: Vec<usize> = vec![0; b.len() + 1];
+	let mut prev: Vec<usize> = (0..(b.len() + 1)).collect();
+	let mut cur = vec![0; b.len() + 1];
+	let mut prev_exceeds = false;
+	for i in 0..a.len() {
+		cur[0] = i + 1;
+		for j in 0..b.len() {
+			let substitution = prev[j] + if a[i] == b[j] { 0 } else { 1 };
+			let mut d = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+			if i > 0 && j > 0 && a[i] == b[j-1] && a[i-1] == b[j] {
+				d = d.min(before[j-1] + 1);
+			}
+			cur[j + 1] = d;
+		}
+		let exceeds = *cur.iter().min().unwrap() > max;
+		if exceeds && prev_exceeds {
+			return None;
+		}
+		prev_exceeds = exceeds;
+		::std::mem::swap(&mut before, &mut prev);
+		::std::mem::swap(&mut prev, &mut cur);
+	}
+	Some(prev[b.len()]).filter(|&d| d <= max)
+}
+
+/// Length of a longest common subsequence: the best global alignment score when a match
+/// scores 1 and everything else 0.
+pub fn lcs_length<T: Eq>(a: &[T], b: &[T]) -> usize {
+	align_score(a, b, &unit_match, 0) as usize
+}
+
+/// A longest common subsequence, the matched columns of that alignment.
+pub fn lcs<T: Eq + Copy>(a: &[T], b: &[T]) -> Vec<T> {
+	let alignment = a.align(b, &unit_match, 0);
+	alignment.columns.iter().filter_map(|c| match *c {
+		Column::Match(x, _) => Some(x),
+		_ => None,
+	}).collect()
+}
+
+/// Number of positions with different elements, None if the lengths differ.
+pub fn hamming<T: Eq>(a: &[T], b: &[T]) -> Option<usize> {
+	hamming_within(a, b, usize::MAX)
+}
+
+/// Hamming distance if the lengths are equal and it is at most `max`.
+pub fn hamming_within<T: Eq>(a: &[T], b: &[T], max: usize) -> Option<usize> {
+	if a.len() != b.len() {
+		return None;
+	}
+	let mut d = 0;
+	for (x, y) in a.iter().zip(b) {
+		if x != y {
+			d += 1;
+			if d > max {
+				return None;
+			}
+		}
+	}
+	Some(d)
+}
+
+#[cfg(test)]
+mod test {
+	use super::{levenshtein, levenshtein_within, osa_distance, osa_within, lcs_length, lcs, hamming, hamming_within};
+	use test_support::random_seq;
+
+	fn chars(s: &str) -> Vec<char> {
+		s.chars().collect()
+	}
+
+	fn random_text(n: usize, seed: u64) -> Vec<u8> {
+		random_seq(b"abc", n, seed)
+	}
+
+	#[test]
+	fn test_levenshtein() {
+		assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+		assert_eq!(levenshtein(&chars("flaw"), &chars("lawn")), 2);
+		assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+		assert_eq!(levenshtein::<char>(&[], &[]), 0);
+		assert_eq!(levenshtein_within(&chars("kitten"), &chars("sitting"), 3), Some(3));
+		assert_eq!(levenshtein_within(&chars("kitten"), &chars("sitting"), 2), None);
+		assert_eq!(levenshtein_within(&chars("a"), &chars("abcd"), 2), None);
+		for seed in 0..20 {
+			let a = random_text(15 + seed as usize % 4, seed);
+			let b = random_text(12, seed + 50);
+			let d = levenshtein(&a, &b);
+			assert_eq!(levenshtein_within(&a, &b, d), Some(d));
+			assert_eq!(levenshtein_within(&a, &b, d + 1), Some(d));
+			if d > 0 {
+				assert_eq!(levenshtein_within(&a, &b, d - 1), None);
+			}
+		}
+	}
+
+	#[test]
+	fn test_osa() {
+		assert_eq!(osa_distance(&chars("ca"), &chars("ac")), 1);
+		assert_eq!(levenshtein(&chars("ca"), &chars("ac")), 2);
+		// the restricted distance may not edit the transposed pair again
+		assert_eq!(osa_distance(&chars("ca"), &chars("abc")), 3);
+		assert_eq!(osa_distance(&chars("abcdef"), &chars("abdcef")), 1);
+		assert_eq!(osa_within(&chars("abcdef"), &chars("badcfe"), 2), None);
+		assert_eq!(osa_within(&chars("abcdef"), &chars("badcfe"), 3), Some(3));
+		for seed in 0..20 {
+			let a = random_text(14, seed);
+			let b = random_text(13 + seed as usize % 3, seed + 70);
+			let d = osa_distance(&a, &b);
+			assert!(d <= levenshtein(&a, &b));
+			assert_eq!(osa_within(&a, &b, d), Some(d));
+			if d > 0 {
+				assert_eq!(osa_within(&a, &b, d - 1), None);
+			}
+		}
+	}
+
+	#[test]
+	fn test_lcs() {
+		assert_eq!(lcs_length(&chars("ABCBDAB"), &chars("BDCABA")), 4);
+		let common = lcs(&chars("ABCBDAB"), &chars("BDCABA"));
+		assert_eq!(common.len(), 4);
+		assert_eq!(lcs_length(&common, &chars("ABCBDAB")), 4);
+		assert_eq!(lcs_length(&common, &chars("BDCABA")), 4);
+		assert_eq!(lcs(&chars("abc"), &chars("xyz")), Vec::<char>::new());
+	}
+
+	#[test]
+	fn test_hamming() {
+		assert_eq!(hamming(&chars("karolin"), &chars("kathrin")), Some(3));
+		assert_eq!(hamming(&chars("abc"), &chars("ab")), None);
+		assert_eq!(hamming_within(&chars("karolin"), &chars("kathrin"), 2), None);
+		assert_eq!(hamming_within(&chars("karolin"), &chars("kathrin"), 3), Some(3));
+	}
+}
@@ -0,0 +1,312 @@
+//! Streaming FASTA and FASTQ readers, one record in memory at a time, and a FASTA writer
+//! that can also write aligned sequences with `-` for gaps.
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use alignment::Alignment;
+
+#[derive(Debug)]
+pub enum FastaError {
+	Io(io::Error),
+	// line number (starting at 1) and a description of the problem
+	Format(usize, String),
+}
+
+impl fmt::Display for FastaError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			FastaError::Io(ref e) => write!(f, "cannot read records: {}", e),
+			FastaError::Format(line, ref msg) => write!(f, "line {}: {}", line, msg),
+		}
+	}
+}
+
+impl From<io::Error> for FastaError {
+	fn from(e: io::Error) -> FastaError {
+		FastaError::Io(e)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+	pub id: String,
+	pub description: Option<String>,
+	pub seq: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastqRecord {
+	pub id: String,
+	pub description: Option<String>,
+	pub seq: Vec<u8>,
+	pub qual: Vec<u8>,
+}
+
+// split a header line (without the leading marker) into id and description
+fn parse_header(header: &[u8]) -> (String, Option<String>) {
+	let header = String::from_utf8_lossy(header);
+	let mut parts = header.trim().splitn(2, char::is_whitespace);
+	let id = parts.next().unwrap_or("").to_string();
+	let description = parts.next().map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+	(id, description)
+}
+
+/// Reads lines as bytes with the line ending stripped, counting line numbers.
+struct Lines<R> {
+	reader: R,
+	line: usize,
+	peeked: Option<Vec<u8>>,
+}
+
+impl<R: BufRead> Lines<R> {
+	fn new(reader: R) -> Lines<R> {
+		Lines {
+			reader,
+			line: 0,
+			peeked: None,
+		}
+	}
+
+	fn next_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+		if let Some(l) = self.peeked.take() {
+			return Ok(Some(l));
+		}
+		let mut buf = Vec::new();
+		if self.reader.read_until(b'\n', &mut buf)? == 0 {
+			return Ok(None);
+		}
+		self.line += 1;
+		while buf.last() == Some(&b'\n') || buf.last() == Some(&b'\r') {
+			buf.pop();
+		}
+		Ok(Some(buf))
+	}
+
+	fn peek_line(&mut self) -> io::Result<Option<&Vec<u8>>> {
+		if self.peeked.is_none() {
+			self.peeked = self.next_line()?;
+		}
+		Ok(self.peeked.as_ref())
+	}
+}
+
+/// Iterator over the records of a FASTA file. Sequences may be wrapped over several lines,
+/// whitespace inside sequence lines is dropped.
+pub struct FastaReader<R> {
+	lines: Lines<R>,
+}
+
+impl<R: BufRead> FastaReader<R> {
+	pub fn new(reader: R) -> FastaReader<R> {
+		FastaReader {
+			lines: Lines::new(reader),
+		}
+	}
+
+	fn read_record(&mut self) -> Result<Option<Record>, FastaError> {
+		let header = loop {
+			match self.lines.next_line()? {
+				None => return Ok(None),
+				Some(ref l) if l.iter().all(|c| c.is_ascii_whitespace()) => continue,
+				Some(l) => {
+					if l[0] != b'>' {
+						return Err(FastaError::Format(self.lines.line, "expected a '>' header line".to_string()));
+					}
+					break l;
+				},
+			}
+		};
+		let (id, description) = parse_header(&header[1..]);
+		let mut seq = Vec::new();
+		while let Some(l) = self.lines.peek_line()? {
+			if l.first() == Some(&b'>') {
+				break;
+			}
+			seq.extend(l.iter().filter(|c| !c.is_ascii_whitespace()));
+			self.lines.peeked = None;
+		}
+		Ok(Some(Record { id, description, seq }))
+	}
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+	type Item = Result<Record, FastaError>;
+
+	fn next(&mut self) -> Option<Result<Record, FastaError>> {
+		match self.read_record() {
+			Ok(r) => r.map(Ok),
+			Err(e) => Some(Err(e)),
+		}
+	}
+}
+
+/// Iterator over the records of a FASTQ file. Sequence and quality may be wrapped over
+/// several lines; the quality is read until it is as long as the sequence, since quality
+/// lines may themselves start with '@' or '+'.
+pub struct FastqReader<R> {
+	lines: Lines<R>,
+}
+
+impl<R: BufRead> FastqReader<R> {
+	pub fn new(reader: R) -> FastqReader<R> {
+		FastqReader {
+			lines: Lines::new(reader),
+		}
+	}
+
+	fn format_error(&self, msg: &str) -> FastaError {
+		FastaError::Format(self.lines.line, msg.to_string())
+	}
+
+	fn read_record(&mut self) -> Result<Option<FastqRecord>, FastaError> {
+		let header = loop {
+			match self.lines.next_line()? {
+				None => return Ok(None),
+				Some(ref l) if l.iter().all(|c| c.is_ascii_whitespace()) => continue,
+				Some(l) => {
+					if l[0] != b'@' {
+						return Err(self.format_error("expected a '@' header line"));
+					}
+					break l;
+				},
+			}
+		};
+		let (id, description) = parse_header(&header[1..]);
+		let mut seq = Vec::new();
+		loop {
+			match self.lines.next_line()? {
+				None => return Err(self.format_error("record ends before the '+' line")),
+				Some(ref l) if l.first() == Some(&b'+') => break,
+				Some(l) => seq.extend(l.iter().filter(|c| !c.is_ascii_whitespace())),
+			}
+		}
+		let mut qual = Vec::with_capacity(seq.len());
+		while qual.len() < seq.len() {
+			match self.lines.next_line()? {
+				None => return Err(self.format_error("quality is shorter than the sequence")),
+				Some(l) => qual.extend(l.iter().filter(|c| !c.is_ascii_whitespace())),
+			}
+		}
+		if qual.len() != seq.len() {
+			return Err(self.format_error("quality is longer than the sequence"));
+		}
+		Ok(Some(FastqRecord { id, description, seq, qual }))
+	}
+}
+
+impl<R: BufRead> Iterator for FastqReader<R> {
+	type Item = Result<FastqRecord, FastaError>;
+
+	fn next(&mut self) -> Option<Result<FastqRecord, FastaError>> {
+		match self.read_record() {
+			Ok(r) => r.map(Ok),
+			Err(e) => Some(Err(e)),
+		}
+	}
+}
+
+/// Writes FASTA records, wrapping sequences at `line_width` symbols (0 for no wrapping).
+pub struct FastaWriter<W> {
+	writer: W,
+	line_width: usize,
+}
+
+impl<W: Write> FastaWriter<W> {
+	pub fn new(writer: W, line_width: usize) -> FastaWriter<W> {
+		FastaWriter { writer, line_width }
+	}
+
+	pub fn write_record(&mut self, id: &str, description: Option<&str>, seq: &[u8]) -> io::Result<()> {
+		match description {
+			Some(d) => writeln!(self.writer, ">{} {}", id, d)?,
+			None => writeln!(self.writer, ">{}", id)?,
+		}
+		let width = if self.line_width == 0 { seq.len().max(1) } else { self.line_width };
+		for chunk in seq.chunks(width) {
+			self.writer.write_all(chunk)?;
+			self.writer.write_all(b"\n")?;
+		}
+		Ok(())
+	}
+
+	/// Write a gapped sequence, with `-` for every gap.
+	pub fn write_aligned(&mut self, id: &str, description: Option<&str>, seq: &[Option<u8>]) -> io::Result<()> {
+		let gapped: Vec<u8> = seq.iter().map(|c| c.unwrap_or(b'-')).collect();
+		self.write_record(id, description, &gapped)
+	}
+
+	/// Write both rows of a pairwise alignment as two records of equal length.
+	pub fn write_alignment(&mut self, id1: &str, id2: &str, alignment: &Alignment<u8>) -> io::Result<()> {
+		self.write_aligned(id1, None, &alignment.seq1())?;
+		self.write_aligned(id2, None, &alignment.seq2())
+	}
+
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+	use super::{FastaReader, FastqReader, FastaWriter, FastaError, Record};
+	use needleman_wunsch::NeedlemanWunsch;
+
+	#[test]
+	fn test_fasta_reader() {
+		let text = "\n>seq1 first record\nACGT\nAC GT\r\n>seq2\n\n>seq3  \nTTTT\n";
+		let records: Vec<Record> = FastaReader::new(Cursor::new(text)).map(|r| r.unwrap()).collect();
+		assert_eq!(records, vec![
+			Record { id: "seq1".to_string(), description: Some("first record".to_string()), seq: b"ACGTACGT".to_vec() },
+			Record { id: "seq2".to_string(), description: None, seq: Vec::new() },
+			Record { id: "seq3".to_string(), description: None, seq: b"TTTT".to_vec() },
+		]);
+		let mut bad = FastaReader::new(Cursor::new("ACGT\n>x\nA\n"));
+		match bad.next() {
+			Some(Err(FastaError::Format(1, _))) => {},
+			other => panic!("unexpected {:?}", other),
+		}
+		assert!(FastaReader::new(Cursor::new("")).next().is_none());
+	}
+
+	#[test]
+	fn test_fastq_reader() {
+		let text = "@r1 desc\nACGT\nAC\n+\n@@II\n+I\n@r2\nA\n+r2\n@\n";
+		let records: Vec<_> = FastqReader::new(Cursor::new(text)).map(|r| r.unwrap()).collect();
+		assert_eq!(records.len(), 2);
+		assert_eq!(records[0].id, "r1");
+		assert_eq!(records[0].description, Some("desc".to_string()));
+		assert_eq!(records[0].seq, b"ACGTAC".to_vec());
+		assert_eq!(records[0].qual, b"@@II+I".to_vec());
+		assert_eq!(records[1].seq, b"A".to_vec());
+		assert_eq!(records[1].qual, b"@".to_vec());
+		let mut truncated = FastqReader::new(Cursor::new("@r1\nACGT\n+\nII\n"));
+		match truncated.next() {
+			Some(Err(FastaError::Format(4, _))) => {},
+			other => panic!("unexpected {:?}", other),
+		}
+		let mut too_long = FastqReader::new(Cursor::new("@r1\nAC\n+\nIII\n"));
+		assert!(too_long.next().unwrap().is_err());
+	}
+
+	#[test]
+	fn test_fasta_writer() {
+		let mut writer = FastaWriter::new(Vec::new(), 4);
+		writer.write_record("a", Some("test"), b"ACGTACGTA").unwrap();
+		writer.write_aligned("b", None, &[Some(b'A'), None, Some(b'C')]).unwrap();
+		let out = String::from_utf8(writer.into_inner()).unwrap();
+		assert_eq!(out, ">a test\nACGT\nACGT\nA\n>b\nA-C\n");
+
+		let records: Vec<_> = FastaReader::new(Cursor::new(">x\nGATTACA\n>y\nGCATGCA\n")).map(|r| r.unwrap().seq).collect();
+		let (s1, s2) = (records[0].clone(), records[1].clone());
+		let similarity = |a: &u8, b: &u8| if a == b { 1 } else { -1 };
+		let res = s1.align(&s2, &similarity, -1);
+		let mut writer = FastaWriter::new(Vec::new(), 0);
+		writer.write_alignment("x", "y", &res).unwrap();
+		let out = writer.into_inner();
+		let back: Vec<_> = FastaReader::new(Cursor::new(out)).map(|r| r.unwrap().seq).collect();
+		assert_eq!(back[0].len(), back[1].len());
+		assert_eq!(back[0].iter().filter(|&&c| c != b'-').cloned().collect::<Vec<_>>(), s1);
+		assert_eq!(back[1].iter().filter(|&&c| c != b'-').cloned().collect::<Vec<_>>(), s2);
+	}
+}
@@ -0,0 +1,186 @@
+//! Global alignment with affine gap penalties (Gotoh's algorithm).
+//! A gap of length k scores gap_open + (k - 1) * gap_extend, so with gap_open == gap_extend
+//! this is the same as the linear gap penalty of NeedlemanWunsch.
+use alignment::Alignment;
+
+// Scores of impossible states, far enough from i64::MIN that adding penalties cannot overflow.
+const NEG_INF: i64 = i64::MIN / 4;
+
+/// The three matrices of the algorithm: the alignment ends with a pair of elements (Match),
+/// with a gap in the second sequence (Up, i.e. consuming an element of the first), or with a
+/// gap in the first sequence (Left).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Layer {
+	Match,
+	Up,
+	Left,
+}
+
+/// One cell of the three matrices: the score in each layer and the layer the optimal path
+/// into that layer came from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct AffineEntry {
+	score: [i64; 3],
+	from: [Layer; 3],
+}
+
+type AffineMatrix = Vec<Vec<AffineEntry>>;
+
+fn layer_index(l: Layer) -> usize {
+	match l {
+		Layer::Match => 0,
+		Layer::Up => 1,
+		Layer::Left => 2,
+	}
+}
+
+// maximum of the candidates, earlier layers win ties
+fn best_of(candidates: [(i64, Layer); 3]) -> (i64, Layer) {
+	let mut best = candidates[0];
+	for &c in &candidates[1..] {
+		if c.0 > best.0 {
+			best = c;
+		}
+	}
+	best
+}
+
+pub trait Gotoh<T> {
+	fn align_affine(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_open: i64, gap_extend: i64) -> Alignment<T>;
+}
+
+impl<T> Gotoh<T> for [T] where T: Eq + Copy + Clone {
+	fn align_affine(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_open: i64, gap_extend: i64) -> Alignment<T> {
+		let mut mat = init_affine_matrix(self.len(), vec.len(), gap_open, gap_extend);
+		fill_affine_matrix(&mut mat, self, vec, similarity, gap_open, gap_extend);
+		affine_traceback(&mat, self, vec)
+	}
+}
+
+/// The first row can only be reached by opening and extending a gap in the first sequence,
+/// the first column only by a gap in the second one. All other boundary states are impossible.
+fn init_affine_matrix(len1: usize, len2: usize, gap_open: i64, gap_extend: i64) -> AffineMatrix {
+	let impossible = AffineEntry {
+		score: [NEG_INF; 3],
+		from: [Layer::Match; 3],
+	};
+	let mut mat = vec![vec![impossible; len2 + 1]; len1 + 1];
+	mat[0][0].score[0] = 0;
+	for (i, row) in mat.iter_mut().enumerate().skip(1) {
+		row[0].score[1] = gap_open + (i as i64 - 1) * gap_extend;
+		row[0].from[1] = if i == 1 { Layer::Match } else { Layer::Up };
+	}
+	for (j, cell) in mat[0].iter_mut().enumerate().skip(1) {
+		cell.score[2] = gap_open + (j as i64 - 1) * gap_extend;
+		cell.from[2] = if j == 1 { Layer::Match } else { Layer::Left };
+	}
+	mat
+}
+
+fn fill_affine_matrix<T>(mat: &mut AffineMatrix, vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_open: i64, gap_extend: i64) {
+	for i in 1..(vec1.len() + 1) {
+		for j in 1..(vec2.len() + 1) {
+			let diag = mat[i-1][j-1].score;
+			let up = mat[i-1][j].score;
+			let left = mat[i][j-1].score;
+			let (m, m_from) = best_of([(diag[0], Layer::Match), (diag[1], Layer::Up), (diag[2], Layer::Left)]);
+			let (x, x_from) = best_of([(up[0] + gap_open, Layer::Match), (up[1] + gap_extend, Layer::Up), (up[2] + gap_open, Layer::Left)]);
+			let (y, y_from) = best_of([(left[0] + gap_open, Layer::Match), (left[1] + gap_open, Layer::Up), (left[2] + gap_extend, Layer::Left)]);
+			mat[i][j] = AffineEntry {
+				score: [m + similarity(&vec1[i-1], &vec2[j-1]), x, y],
+				from: [m_from, x_from, y_from],
+			};
+		}
+	}
+}
+
+/// Start from the best layer of the last cell and walk back, switching matrices as recorded.
+fn affine_traceback<T>(mat: &AffineMatrix, vec1: &[T], vec2: &[T]) -> Alignment<T> where T: Eq + Copy {
+	let mut i = vec1.len();
+	let mut j = vec2.len();
+	let last = mat[i][j].score;
+	let (score, mut layer) = best_of([(last[0], Layer::Match), (last[1], Layer::Up), (last[2], Layer::Left)]);
+	let mut res1 = Vec::new();
+	let mut res2 = Vec::new();
+	while i > 0 || j > 0 {
+		let from = mat[i][j].from[layer_index(layer)];
+		match layer {
+			Layer::Match => {
+				res1.push(Some(vec1[i-1]));
+				res2.push(Some(vec2[j-1]));
+				i -= 1;
+				j -= 1;
+			},
+			Layer::Up => {
+				res1.push(Some(vec1[i-1]));
+				res2.push(None);
+				i -= 1;
+			},
+			Layer::Left => {
+				res1.push(None);
+				res2.push(Some(vec2[j-1]));
+				j -= 1;
+			},
+		}
+		layer = from;
+	}
+	res1.reverse();
+	res2.reverse();
+	Alignment::from_pair(res1, res2, score)
+}
+
+#[cfg(test)]
+mod test {
+	use super::Gotoh;
+	use needleman_wunsch::NeedlemanWunsch;
+
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 2 } else { -2 }
+	}
+
+	fn chars(s: &str) -> Vec<char> {
+		s.chars().collect()
+	}
+
+	fn to_string(v: &[Option<char>]) -> String {
+		v.iter().map(|c| c.unwrap_or('-')).collect()
+	}
+
+	#[test]
+	fn test_affine_single_gap() {
+		let res = chars("ABCDEFGH").align_affine(&chars("ABGH"), &similarity, -5, -1);
+		assert_eq!(to_string(&res.seq1()), "ABCDEFGH");
+		assert_eq!(to_string(&res.seq2()), "AB----GH");
+		assert_eq!(res.score, 0);
+		assert_eq!(res.affine_score(&similarity, -5, -1), 0);
+		// a cheap gap opening makes splitting the gap worthwhile
+		let res = chars("AXBYC").align_affine(&chars("ABC"), &similarity, -1, -3);
+		assert_eq!(to_string(&res.seq2()), "A-B-C");
+		let res = chars("AXBYC").align_affine(&chars("ABC"), &similarity, -4, -1);
+		assert_eq!(res.affine_score(&similarity, -4, -1), 6 - 4 - 4);
+	}
+
+	#[test]
+	fn test_affine_boundaries() {
+		let res = chars("ABC").align_affine(&[], &similarity, -5, -1);
+		assert_eq!(res.to_pair(), (vec![Some('A'), Some('B'), Some('C')], vec![None, None, None]));
+		assert_eq!(res.score, -7);
+		let res = chars("").align_affine(&chars("AB"), &similarity, -5, -1);
+		assert_eq!(res.to_pair(), (vec![None, None], vec![Some('A'), Some('B')]));
+		// the leading gap must be charged like any other gap
+		let res = chars("XXXXABCD").align_affine(&chars("ABCD"), &similarity, -3, -1);
+		assert_eq!(to_string(&res.seq2()), "----ABCD");
+		assert_eq!(res.affine_score(&similarity, -3, -1), 8 - 3 - 3);
+	}
+
+	#[test]
+	fn test_affine_matches_linear() {
+		for &(a, b) in &[("what", "white"), ("ab", "aeb"), ("GATTACA", "GCATGCU"), ("kitten", "sitting")] {
+			let (v1, v2) = (chars(a), chars(b));
+			let linear = v1.align(&v2, &similarity, -3);
+			let affine = v1.align_affine(&v2, &similarity, -3, -3);
+			assert_eq!(affine.score, linear.score);
+			assert_eq!(affine.affine_score(&similarity, -3, -3), affine.score);
+		}
+	}
+}
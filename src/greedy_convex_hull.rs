@@ -1,10 +1,11 @@
 use point::{Point, sort_points, Direction};
+use coordinate::Coordinate;
 
 // see http://i.imgur.com/C2zng5r.png
 // I have done this from a slightly different perspective,
 // i.e. intead of using the lowest point as the head, I used the leftmost.
-fn graham_scan(points: &mut Vec<Point>) -> Vec<Point> {
-    let mut hull: Vec<Point> = Vec::new();
+pub fn graham_scan<T>(points: &mut Vec<Point<T>>) -> Vec<Point<T>> where T: Coordinate {
+    let mut hull: Vec<Point<T>> = Vec::new();
     sort_points(points);
     hull.push(points[0]);
     hull.push(points[1]);
@@ -59,4 +60,17 @@ mod test {
 		assert_eq!(hull, hull_should_be);
     }
 
+    #[test]
+    fn test_graham_scan_integer() {
+        let mut points: Vec<Point<i64>> = Vec::new();
+		for i in 1..10 {
+			points.push(Point::from_coords(i, i));
+			points.push(Point::from_coords(i, -i));
+			points.push(Point::from_coords(i, 0));
+		}
+		points.push(Point::from_coords(0, 0));
+		let hull = graham_scan(&mut points);
+		assert_eq!(hull, vec![Point::from_coords(0, 0), Point::from_coords(9, -9), Point::from_coords(9, 9)]);
+    }
+
 }
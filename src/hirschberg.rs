@@ -0,0 +1,102 @@
+//! Hirschberg's divide and conquer alignment: the same optimal global alignment score as
+//! NeedlemanWunsch::align, but only O(len1 + len2) memory besides the result.
+use needleman_wunsch::{NeedlemanWunsch, last_row_scores};
+use alignment::Alignment;
+
+pub trait Hirschberg<T> {
+	fn align_linear_space(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Alignment<T>;
+}
+
+impl<T> Hirschberg<T> for [T] where T: Eq + Copy + Clone {
+	fn align_linear_space(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Alignment<T> {
+		let mut res = (Vec::with_capacity(self.len() + vec.len()), Vec::with_capacity(self.len() + vec.len()));
+		hirschberg(self, vec, similarity, gap_penalty, &mut res);
+		let mut alignment = Alignment::from_pair(res.0, res.1, 0);
+		alignment.score = alignment.linear_score(similarity, gap_penalty);
+		alignment
+	}
+}
+
+fn hirschberg<T>(vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64,
+                 res: &mut (Vec<Option<T>>, Vec<Option<T>>)) where T: Eq + Copy + Clone {
+	if vec1.is_empty() || vec2.is_empty() {
+		res.0.extend(vec1.iter().map(|&x| Some(x)).chain(vec2.iter().map(|_| None)));
+		res.1.extend(vec1.iter().map(|_| None).chain(vec2.iter().map(|&x| Some(x))));
+		return;
+	}
+	if vec1.len() == 1 || vec2.len() == 1 {
+		// the full matrix is only two rows (or columns) here
+		let (a, b) = vec1.align(vec2, similarity, gap_penalty).to_pair();
+		res.0.extend(a);
+		res.1.extend(b);
+		return;
+	}
+	// split the first sequence in half, and find where the optimal path crosses the middle row
+	let mid = vec1.len() / 2;
+	let forward = last_row_scores(&vec1[..mid], vec2, similarity, gap_penalty);
+	let rev1: Vec<T> = vec1[mid..].iter().rev().cloned().collect();
+	let rev2: Vec<T> = vec2.iter().rev().cloned().collect();
+	let backward = last_row_scores(&rev1, &rev2, similarity, gap_penalty);
+	let n = vec2.len();
+	let split = (0..(n + 1)).max_by_key(|&j| (forward[j] + backward[n - j], ::std::cmp::Reverse(j))).unwrap();
+	hirschberg(&vec1[..mid], &vec2[..split], similarity, gap_penalty, res);
+	hirschberg(&vec1[mid..], &vec2[split..], similarity, gap_penalty, res);
+}
+
+#[cfg(test)]
+mod test {
+	use super::Hirschberg;
+	use needleman_wunsch::{NeedlemanWunsch, align_score};
+	use test_support::random_seq;
+
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 1 } else { -1 }
+	}
+
+	fn random_dna(n: usize, seed: u64) -> Vec<char> {
+		random_seq(&['A', 'C', 'G', 'T'], n, seed)
+	}
+
+	fn check(v1: &[char], v2: &[char], gap_penalty: i64) {
+		let full = v1.align(v2, &similarity, gap_penalty);
+		let linear = v1.align_linear_space(v2, &similarity, gap_penalty);
+		// the result must be an alignment of the two inputs
+		assert_eq!(linear.seq1().into_iter().flatten().collect::<Vec<_>>(), v1.to_vec());
+		assert_eq!(linear.seq2().into_iter().flatten().collect::<Vec<_>>(), v2.to_vec());
+		assert_eq!(linear.score, full.score);
+		assert_eq!(linear.score, align_score(v1, v2, &similarity, gap_penalty));
+		assert_eq!(linear.linear_score(&similarity, gap_penalty), full.linear_score(&similarity, gap_penalty));
+	}
+
+	#[test]
+	fn test_hirschberg_small() {
+		let what: Vec<_> = "what".chars().collect();
+		let white: Vec<_> = "white".chars().collect();
+		check(&what, &white, -1);
+		assert_eq!(what.align_linear_space(&white, &similarity, -1).to_pair(),
+		           (vec![Some('w'), Some('h'), Some('a'), Some('t'), None], vec![Some('w'), Some('h'), Some('i'), Some('t'), Some('e')]));
+		check(&what, &[], -1);
+		check(&[], &white, -2);
+		check(&['x'], &white, -1);
+	}
+
+	#[test]
+	fn test_hirschberg_random() {
+		for &(n, m, gap) in &[(50, 60, -1), (200, 150, -2), (301, 333, -1)] {
+			let v1 = random_dna(n, n as u64);
+			let mut v2 = v1.clone();
+			// mutate, delete and insert some positions so that the sequences are related
+			let noise = random_dna(m, 1000 + m as u64);
+			for (k, c) in noise.iter().enumerate().filter(|&(k, _)| k % 7 == 0) {
+				let pos = (k * 13) % v2.len();
+				match *c {
+					'A' => { v2[pos] = 'T'; },
+					'C' => { v2.remove(pos); },
+					_ => { v2.insert(pos, *c); },
+				}
+			}
+			check(&v1, &v2, gap);
+			check(&v1, &random_dna(m, 7), gap);
+		}
+	}
+}
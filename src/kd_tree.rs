@@ -0,0 +1,214 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use point::Point;
+use rect::Rect;
+use coordinate::Coordinate;
+use definite_num::DefinitelyANumber;
+
+/// A static 2-d tree. The points are stored in a single vector laid out as an implicit
+/// balanced tree: the root of the subtree over points[lo..hi] is the median points[(lo + hi) / 2],
+/// split on x at even depths and on y at odd depths.
+pub struct KdTree<T = DefinitelyANumber> {
+    points: Vec<Point<T>>,
+}
+
+fn cmp_axis<T>(a: &Point<T>, b: &Point<T>, axis: usize) -> Ordering where T: Coordinate {
+    if axis == 0 {
+        a.x.cmp(&b.x)
+    } else {
+        a.y.cmp(&b.y)
+    }
+}
+
+// signed distance along the splitting axis, negative when p is on the lower side
+fn axis_offset<T>(p: &Point<T>, node: &Point<T>, axis: usize) -> f64 where T: Coordinate {
+    if axis == 0 {
+        p.x.to_f64() - node.x.to_f64()
+    } else {
+        p.y.to_f64() - node.y.to_f64()
+    }
+}
+
+fn squared_distance<T>(a: &Point<T>, b: &Point<T>) -> f64 where T: Coordinate {
+    (a.x.to_f64() - b.x.to_f64()).powi(2) + (a.y.to_f64() - b.y.to_f64()).powi(2)
+}
+
+fn build<T>(points: &mut [Point<T>], depth: usize) where T: Coordinate {
+    if points.len() <= 1 {
+        return;
+    }
+    let mid = points.len() / 2;
+    let axis = depth % 2;
+    points.select_nth_unstable_by(mid, |a, b| cmp_axis(a, b, axis));
+    let (left, right) = points.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+impl<T> KdTree<T> where T: Coordinate {
+    pub fn new(mut points: Vec<Point<T>>) -> KdTree<T> {
+        build(&mut points, 0);
+        KdTree { points }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn nearest(&self, target: &Point<T>) -> Option<&Point<T>> {
+        self.nearest_k(target, 1).into_iter().next()
+    }
+
+    /// The k points closest to target, closest first.
+    pub fn nearest_k(&self, target: &Point<T>, k: usize) -> Vec<&Point<T>> {
+        if k == 0 {
+            return Vec::new();
+        }
+        // max-heap on the distance, so the worst of the current candidates is on top
+        let mut heap: BinaryHeap<(DefinitelyANumber, usize)> = BinaryHeap::new();
+        self.search_nearest(target, k, 0, self.points.len(), 0, &mut heap);
+        heap.into_sorted_vec().into_iter().map(|(_, i)| &self.points[i]).collect()
+    }
+
+    fn search_nearest(&self, target: &Point<T>, k: usize, lo: usize, hi: usize, depth: usize,
+                      heap: &mut BinaryHeap<(DefinitelyANumber, usize)>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let node = &self.points[mid];
+        let d = DefinitelyANumber::new(squared_distance(target, node)).unwrap();
+        if heap.len() < k {
+            heap.push((d, mid));
+        } else if d < heap.peek().unwrap().0 {
+            heap.pop();
+            heap.push((d, mid));
+        }
+        let offset = axis_offset(target, node, depth % 2);
+        // descend into the side containing the target first, the other side only if it may still hold a closer point
+        let (near, far) = if offset < 0.0 { ((lo, mid), (mid + 1, hi)) } else { ((mid + 1, hi), (lo, mid)) };
+        self.search_nearest(target, k, near.0, near.1, depth + 1, heap);
+        if heap.len() < k || offset.powi(2) < heap.peek().unwrap().0.to_f64() {
+            self.search_nearest(target, k, far.0, far.1, depth + 1, heap);
+        }
+    }
+
+    /// All points within the given Euclidean distance of center, boundary included.
+    pub fn within_radius(&self, center: &Point<T>, radius: f64) -> Vec<&Point<T>> {
+        let mut res = Vec::new();
+        self.search_radius(center, radius, 0, self.points.len(), 0, &mut res);
+        res
+    }
+
+    fn search_radius<'a>(&'a self, center: &Point<T>, radius: f64, lo: usize, hi: usize, depth: usize,
+                         res: &mut Vec<&'a Point<T>>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let node = &self.points[mid];
+        if squared_distance(center, node) <= radius * radius {
+            res.push(node);
+        }
+        let offset = axis_offset(center, node, depth % 2);
+        if offset <= radius {
+            self.search_radius(center, radius, lo, mid, depth + 1, res);
+        }
+        if offset >= -radius {
+            self.search_radius(center, radius, mid + 1, hi, depth + 1, res);
+        }
+    }
+
+    /// All points inside the rectangle, boundary included.
+    pub fn range(&self, rect: &Rect<T>) -> Vec<&Point<T>> {
+        let mut res = Vec::new();
+        self.search_range(rect, 0, self.points.len(), 0, &mut res);
+        res
+    }
+
+    fn search_range<'a>(&'a self, rect: &Rect<T>, lo: usize, hi: usize, depth: usize, res: &mut Vec<&'a Point<T>>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let node = &self.points[mid];
+        if rect.contains(node) {
+            res.push(node);
+        }
+        let axis = depth % 2;
+        // everything left of mid is <= node on the axis, everything right of it is >= node
+        if cmp_axis(&rect.min, node, axis) != Ordering::Greater {
+            self.search_range(rect, lo, mid, depth + 1, res);
+        }
+        if cmp_axis(&rect.max, node, axis) != Ordering::Less {
+            self.search_range(rect, mid + 1, hi, depth + 1, res);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use point::Point;
+    use rect::Rect;
+    use kd_tree::KdTree;
+    use test_support::Lcg;
+
+    // deterministic pseudo random points on a 1000 x 1000 grid
+    fn random_points(n: usize) -> Vec<Point<i64>> {
+        let mut rng = Lcg::new(42);
+        (0..n).map(|_| Point::from_coords(rng.below(1000) as i64, rng.below(1000) as i64)).collect()
+    }
+
+    #[test]
+    fn test_kd_tree_nearest() {
+        let points = random_points(2000);
+        let tree = KdTree::new(points.clone());
+        assert_eq!(tree.len(), 2000);
+        for target in random_points(50).iter().map(|p| Point::from_coords(p.y, p.x)) {
+            let mut by_distance = points.clone();
+            by_distance.sort_by(|a, b| a.distance(&target).partial_cmp(&b.distance(&target)).unwrap());
+            let best = tree.nearest(&target).unwrap();
+            assert_eq!(best.distance(&target), by_distance[0].distance(&target));
+            let k_best: Vec<f64> = tree.nearest_k(&target, 7).iter().map(|p| p.distance(&target)).collect();
+            let k_should_be: Vec<f64> = by_distance[..7].iter().map(|p| p.distance(&target)).collect();
+            assert_eq!(k_best, k_should_be);
+        }
+        let empty: KdTree<i64> = KdTree::new(Vec::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.nearest(&Point::from_coords(0, 0)), None);
+        assert_eq!(tree.nearest_k(&Point::from_coords(0, 0), 3000).len(), 2000);
+    }
+
+    #[test]
+    fn test_kd_tree_range() {
+        let points = random_points(2000);
+        let tree = KdTree::new(points.clone());
+        let rect = Rect::new(Point::from_coords(100, 250), Point::from_coords(400, 300));
+        let mut found: Vec<_> = tree.range(&rect).into_iter().cloned().collect();
+        let mut should_be: Vec<_> = points.iter().filter(|p| rect.contains(p)).cloned().collect();
+        found.sort();
+        should_be.sort();
+        assert!(!should_be.is_empty());
+        assert_eq!(found, should_be);
+
+        let center = Point::from_coords(500, 500);
+        let mut found: Vec<_> = tree.within_radius(&center, 60.0).into_iter().cloned().collect();
+        let mut should_be: Vec<_> = points.iter().filter(|p| p.distance(&center) <= 60.0).cloned().collect();
+        found.sort();
+        should_be.sort();
+        assert!(!should_be.is_empty());
+        assert_eq!(found, should_be);
+    }
+
+    #[test]
+    fn test_kd_tree_float() {
+        let tree = KdTree::new(vec![Point::new(0.0, 0.0), Point::new(1.0, 1.0), Point::new(2.5, 0.5), Point::new(-1.0, 3.0)]);
+        assert_eq!(tree.nearest(&Point::new(2.0, 0.0)), Some(&Point::new(2.5, 0.5)));
+        assert_eq!(tree.within_radius(&Point::new(0.0, 0.0), 1.5).len(), 2);
+    }
+}
@@ -1,15 +1,45 @@
 #![feature(stmt_expr_attributes)]
+#![cfg_attr(test, feature(test))]
+
+#[cfg(test)]
+extern crate test;
 
 #[macro_use]
 pub mod naive_convex_hull;
 pub mod greedy_convex_hull;
 pub mod definite_num;
+pub mod rational;
+pub mod coordinate;
 pub mod point;
 pub mod triangle;
+pub mod rect;
+pub mod kd_tree;
+pub mod quadtree;
+pub mod r_tree;
 pub mod max_elem;
+pub mod alignment;
 pub mod needleman_wunsch;
+pub mod smith_waterman;
+pub mod gotoh;
+pub mod hirschberg;
+pub mod banded;
+pub mod msa;
+pub mod edit_distance;
+pub mod myers;
+pub mod wavefront;
+pub mod striped;
+pub mod cigar;
+pub mod text;
+pub mod matrix_view;
+pub mod statistics;
+pub mod diff;
+pub mod substitution_matrix;
+pub mod fasta;
 pub mod zm;
 pub mod insertion_sort;
 
+#[cfg(test)]
+mod test_support;
+
 #[macro_use]
 extern crate itertools;
@@ -5,8 +5,16 @@ use algorithms_in_a_nutshell::point::Point;
 use algorithms_in_a_nutshell::naive_convex_hull; 
 use std::collections::BTreeSet;
 use algorithms_in_a_nutshell::needleman_wunsch::NeedlemanWunsch;
+use algorithms_in_a_nutshell::diff::{unified_diff, DiffOptions};
+use std::env;
+use std::fs;
+use std::process;
 
 fn main() {
+		let args: Vec<String> = env::args().skip(1).collect();
+		if args.first().map(|a| a.as_str()) == Some("diff") {
+			process::exit(diff_files(&args[1..]));
+		}
 		fn similarity(c1: &char, c2: &char) -> i64 {
 			if c1 == c2 {
 				1
@@ -14,24 +22,62 @@ fn main() {
 				-1
 			}
 		}
-		let mut vec1: Vec<char>  = "what".chars().collect();
-		let mut vec2: Vec<char>  = "white".chars().collect();
-		let res = vec1.align(&mut vec2, &similarity, -1);
-		let mut s0 = String::new();
-		let mut s1 = String::new();
-		use std::fmt::Write;
-		for x in res.0 {
-			match x {
-				Some(c) => write!(&mut s0, "{}", c).unwrap(),
-				_ => write!(&mut s0, "{}", '_').unwrap(),
+		let vec1: Vec<char>  = "what".chars().collect();
+		let vec2: Vec<char>  = "white".chars().collect();
+		let res = vec1.align(&vec2, &similarity, -1);
+		println!("{}", res);
+		println!("score: {}, identity: {:.2}", res.score, res.identity());
+}
+
+/// `diff [--words] [-U lines] OLD NEW`: print the unified diff of two files. The exit status
+/// is 0 if they are equal, 1 if they differ and 2 on errors, like diff(1).
+fn diff_files(args: &[String]) -> i32 {
+		let usage = "usage: diff [--words] [-U lines] OLD NEW";
+		let mut options = DiffOptions::default();
+		let mut files = Vec::new();
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--words" => options.word_diff = true,
+				"-U" => match args.next().and_then(|n| n.parse().ok()) {
+					Some(n) => options.context = n,
+					None => {
+						eprintln!("{}", usage);
+						return 2;
+					},
+				},
+				// the count attached to the option, -U5
+				_ if arg.starts_with("-U") => match arg[2..].parse() {
+					Ok(n) => options.context = n,
+					Err(_) => {
+						eprintln!("{}", usage);
+						return 2;
+					},
+				},
+				// there is no other option, and no reading from standard input for `-`
+				_ if arg.starts_with('-') => {
+					eprintln!("diff: unknown option {}", arg);
+					eprintln!("{}", usage);
+					return 2;
+				},
+				_ => files.push(arg),
 			}
 		}
-		for x in res.1 {
-			match x {
-				Some(c) => write!(&mut s1, "{}", c).unwrap(),
-				_ => write!(&mut s1, "{}", '_').unwrap(),
+		if files.len() != 2 {
+			eprintln!("{}", usage);
+			return 2;
+		}
+		let mut texts = Vec::new();
+		for name in &files {
+			match fs::read_to_string(name) {
+				Ok(text) => texts.push(text),
+				Err(e) => {
+					eprintln!("{}: {}", name, e);
+					return 2;
+				},
 			}
 		}
-		println!("{}", s0);
-		println!("{}", s1);
-}
\ No newline at end of file
+		let diff = unified_diff(files[0], files[1], &texts[0], &texts[1], options);
+		print!("{}", diff);
+		if diff.is_empty() { 0 } else { 1 }
+}
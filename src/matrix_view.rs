@@ -0,0 +1,271 @@
+//! Rendering of the Needleman-Wunsch matrix for teaching material and for debugging scoring
+//! schemes: every cell shows its score and the move the traceback takes from it, the cells
+//! of the optimal alignment are highlighted. The same view is written as plain text (also its
+//! `Display`), as a standalone HTML page or as an SVG image.
+use std::fmt;
+
+use needleman_wunsch::{AlignMatrix, AlignMode, Direction, TieBreak, fill_align_matrix, init_align_matrix, traceback_start};
+
+/// Side length of a cell in the SVG, in pixels.
+const CELL: usize = 48;
+
+pub struct MatrixView {
+	mat: AlignMatrix,
+	// row labels, "-" for the empty prefix and then the elements of the first sequence, and
+	// likewise the column labels from the second sequence
+	labels1: Vec<String>,
+	labels2: Vec<String>,
+	// the cells of the optimal alignment from the first one to the last one, and the same
+	// cells as a grid of the matrix dimensions to look them up while drawing
+	path: Vec<(usize, usize)>,
+	on_path: Vec<Vec<bool>>,
+	score: i64,
+}
+
+fn arrow(d: Direction) -> &'static str {
+	match d {
+		Direction::Match => "↖",
+		Direction::Up => "↑",
+		Direction::Left => "←",
+		Direction::Undefined => "",
+	}
+}
+
+/// Escape text for HTML and SVG.
+fn escape(s: &str) -> String {
+	let mut res = String::with_capacity(s.len());
+	for c in s.chars() {
+		match c {
+			'&' => res.push_str("&amp;"),
+			'<' => res.push_str("&lt;"),
+			'>' => res.push_str("&gt;"),
+			'"' => res.push_str("&quot;"),
+			_ => res.push(c),
+		}
+	}
+	res
+}
+
+impl MatrixView {
+	/// Fill the matrix of the two sequences in the given mode and trace the alignment the
+	/// aligners return. Byte sequences can be mapped to chars first to get readable labels.
+	pub fn new<T>(seq1: &[T], seq2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, mode: AlignMode) -> MatrixView
+		where T: fmt::Display {
+		let mut mat = init_align_matrix(seq1.len(), seq2.len(), gap_penalty, mode);
+		fill_align_matrix(&mut mat, seq1, seq2, similarity, gap_penalty, TieBreak::default());
+		let labels = |seq: &[T]| -> Vec<String> {
+			Some("-".to_string()).into_iter().chain(seq.iter().map(|x| x.to_string())).collect()
+		};
+		let (labels1, labels2) = (labels(seq1), labels(seq2));
+		MatrixView::from_matrix(mat, labels1, labels2, Some(mode))
+	}
+
+	/// View of a filled matrix. Missing labels are left blank. The path is traced for the
+	/// mode the matrix was filled in, without a mode there is no path and the score is the one
+	/// of the last cell.
+	pub(crate) fn from_matrix(mat: AlignMatrix, labels1: Vec<String>, labels2: Vec<String>, mode: Option<AlignMode>) -> MatrixView {
+		let (n, m) = (mat.len() - 1, mat[0].len() - 1);
+		let mut on_path = vec![vec![false; m + 1]; n + 1];
+		let mode = match mode {
+			Some(mode) => mode,
+			None => {
+				let score = mat[n][m].score;
+				return MatrixView { mat, labels1, labels2, path: Vec::new(), on_path, score };
+			},
+		};
+		let (mut i, mut j) = traceback_start(&mat, mode);
+		let score = mat[i][j].score;
+		// the unaligned suffix runs along the last row or column to the last cell
+		let mut path: Vec<(usize, usize)> = ((i + 1)..(n + 1)).map(|k| (k, m)).rev().collect();
+		path.extend(((j + 1)..(m + 1)).map(|k| (n, k)).rev());
+		loop {
+			path.push((i, j));
+			match mat[i][j].direction {
+				Direction::Match => { i -= 1; j -= 1; },
+				Direction::Up => i -= 1,
+				Direction::Left => j -= 1,
+				Direction::Undefined => break,
+			}
+		}
+		path.reverse();
+		for &(i, j) in &path {
+			on_path[i][j] = true;
+		}
+		MatrixView { mat, labels1, labels2, path, on_path, score }
+	}
+
+	/// Score of the optimal alignment.
+	pub fn score(&self) -> i64 {
+		self.score
+	}
+
+	/// Cells of the optimal alignment as (row, column), from (0, 0) on.
+	pub fn path(&self) -> &[(usize, usize)] {
+		&self.path
+	}
+
+	fn on_path(&self, i: usize, j: usize) -> bool {
+		self.on_path[i][j]
+	}
+
+	fn label(labels: &[String], k: usize) -> &str {
+		labels.get(k).map_or("", |l| l.as_str())
+	}
+
+	fn cell(&self, i: usize, j: usize) -> String {
+		let entry = &self.mat[i][j];
+		format!("{}{}", arrow(entry.direction), entry.score)
+	}
+
+	/// Standalone HTML page with the matrix as a table, path cells have the class `path`.
+	pub fn to_html(&self) -> String {
+		let mut s = String::new();
+		s.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Alignment matrix</title>\n<style>\n");
+		s.push_str("table { border-collapse: collapse; font-family: monospace; }\n");
+		s.push_str("th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }\n");
+		s.push_str("th { background: #eee; }\n");
+		s.push_str("td.path { background: #ffd866; font-weight: bold; }\n");
+		s.push_str("</style>\n</head>\n<body>\n<table>\n<tr><th></th>");
+		for j in 0..self.mat[0].len() {
+			s.push_str(&format!("<th>{}</th>", escape(MatrixView::label(&self.labels2, j))));
+		}
+		s.push_str("</tr>\n");
+		for (i, row) in self.mat.iter().enumerate() {
+			s.push_str(&format!("<tr><th>{}</th>", escape(MatrixView::label(&self.labels1, i))));
+			for j in 0..row.len() {
+				let class = if self.on_path(i, j) { " class=\"path\"" } else { "" };
+				s.push_str(&format!("<td{}>{}</td>", class, self.cell(i, j)));
+			}
+			s.push_str("</tr>\n");
+		}
+		s.push_str(&format!("</table>\n<p>Score: {}</p>\n</body>\n</html>\n", self.score()));
+		s
+	}
+
+	/// SVG image of the matrix, the path is filled and connected by a line.
+	pub fn to_svg(&self) -> String {
+		let (rows, cols) = (self.mat.len(), self.mat[0].len());
+		let center = |k: usize| (k + 1) * CELL + CELL / 2;
+		let mut s = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"14\" text-anchor=\"middle\" dominant-baseline=\"central\">\n",
+			(cols + 1) * CELL, (rows + 1) * CELL);
+		for j in 0..cols {
+			s.push_str(&format!("<text x=\"{}\" y=\"{}\" font-weight=\"bold\">{}</text>\n", center(j), CELL / 2, escape(MatrixView::label(&self.labels2, j))));
+		}
+		for i in 0..rows {
+			s.push_str(&format!("<text x=\"{}\" y=\"{}\" font-weight=\"bold\">{}</text>\n", CELL / 2, center(i), escape(MatrixView::label(&self.labels1, i))));
+		}
+		for (i, row) in self.mat.iter().enumerate() {
+			for (j, entry) in row.iter().enumerate() {
+				let (x, y) = ((j + 1) * CELL, (i + 1) * CELL);
+				let fill = if self.on_path(i, j) { "#ffd866" } else { "#ffffff" };
+				s.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#cccccc\"/>\n", x, y, CELL, CELL, fill));
+				s.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", center(j), center(i), entry.score));
+				if entry.direction != Direction::Undefined {
+					s.push_str(&format!("<text x=\"{}\" y=\"{}\" font-size=\"10\" fill=\"#888888\">{}</text>\n", x + 8, y + 8, arrow(entry.direction)));
+				}
+			}
+		}
+		let points: Vec<String> = self.path.iter().map(|&(i, j)| format!("{},{}", center(j), center(i))).collect();
+		s.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"#d33333\" stroke-width=\"2\"/>\n</svg>\n", points.join(" ")));
+		s
+	}
+}
+
+/// Plain text: labels around the matrix, every cell as its move and score, path cells in
+/// brackets.
+impl fmt::Display for MatrixView {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let cells: Vec<Vec<String>> = self.mat.iter().enumerate().map(|(i, row)| {
+			(0..row.len()).map(|j| {
+				let cell = self.cell(i, j);
+				if self.on_path(i, j) { format!("[{}]", cell) } else { cell }
+			}).collect()
+		}).collect();
+		let width = cells.iter().flatten().chain(self.labels2.iter()).map(|c| c.chars().count()).max().unwrap_or(0);
+		let label_width = self.labels1.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+		let mut header = format!("{:w$}", "", w = label_width);
+		for j in 0..self.mat[0].len() {
+			header.push_str(&format!(" {:>w$}", MatrixView::label(&self.labels2, j), w = width));
+		}
+		write!(f, "{}", header.trim_end())?;
+		for (i, row) in cells.iter().enumerate() {
+			let mut line = format!("{:w$}", MatrixView::label(&self.labels1, i), w = label_width);
+			for cell in row {
+				line.push_str(&format!(" {:>w$}", cell, w = width));
+			}
+			write!(f, "\n{}", line)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::MatrixView;
+	use needleman_wunsch::{AlignMode, NeedlemanWunsch, TieBreak, fill_align_matrix, init_align_matrix};
+
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 1 } else { -1 }
+	}
+
+	#[test]
+	fn test_text() {
+		let vec1: Vec<_> = "ab".chars().collect();
+		let vec2: Vec<_> = "aeb".chars().collect();
+		let view = MatrixView::new(&vec1, &vec2, &similarity, -1, AlignMode::Global);
+		assert_eq!(view.path(), &[(0, 0), (1, 1), (1, 2), (2, 3)]);
+		assert_eq!(view.score(), 1);
+		assert_eq!(format!("{}", view), [
+			"     -    a    e    b",
+			"-  [0]  ←-1  ←-2  ←-3",
+			"a  ↑-1 [↖1] [←0]  ←-1",
+			"b  ↑-2   ↑0   ↖0 [↖1]",
+		].join("\n"));
+	}
+
+	#[test]
+	fn test_path_matches_alignment() {
+		let vec1: Vec<_> = "ttttgattacatttt".chars().collect();
+		let vec2: Vec<_> = "gattaca".chars().collect();
+		for &mode in &[AlignMode::Global, AlignMode::glocal(), AlignMode::overlap()] {
+			let view = MatrixView::new(&vec1, &vec2, &similarity, -1, mode);
+			let alignment = vec1.align_mode(&vec2, &similarity, -1, mode);
+			// one step per column, from the first cell to the last one
+			assert_eq!(view.path().len(), alignment.len() + 1);
+			assert_eq!(view.path()[0], (0, 0));
+			assert_eq!(*view.path().last().unwrap(), (vec1.len(), vec2.len()));
+			assert_eq!(view.score(), alignment.score);
+		}
+	}
+
+	#[test]
+	fn test_html_and_svg() {
+		let vec1: Vec<_> = "<a".chars().collect();
+		let vec2: Vec<_> = "a&".chars().collect();
+		let view = MatrixView::new(&vec1, &vec2, &similarity, -1, AlignMode::Global);
+		let html = view.to_html();
+		assert!(html.starts_with("<!DOCTYPE html>"));
+		assert!(html.contains("<th>&lt;</th>") && html.contains("<th>&amp;</th>"));
+		assert_eq!(html.matches("<td").count(), 9);
+		assert_eq!(html.matches("class=\"path\"").count(), view.path().len());
+		assert!(html.contains(&format!("Score: {}", view.score())));
+		let svg = view.to_svg();
+		assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+		assert_eq!(svg.matches("<rect").count(), 9);
+		assert_eq!(svg.matches("fill=\"#ffd866\"").count(), view.path().len());
+		assert!(svg.contains(">&lt;</text>"));
+	}
+
+	#[test]
+	fn test_without_path() {
+		let vec1: Vec<_> = "ab".chars().collect();
+		let vec2: Vec<_> = "aeb".chars().collect();
+		let mut mat = init_align_matrix(vec1.len(), vec2.len(), -1, AlignMode::Global);
+		fill_align_matrix(&mut mat, &vec1, &vec2, &similarity, -1, TieBreak::default());
+		let view = MatrixView::from_matrix(mat, Vec::new(), Vec::new(), None);
+		assert!(view.path().is_empty());
+		assert_eq!(view.score(), 1);
+		assert!(!format!("{}", view).contains('['));
+		assert!(!view.to_html().contains("class=\"path\""));
+	}
+}
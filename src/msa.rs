@@ -0,0 +1,350 @@
+//! Progressive multiple sequence alignment: pairwise distances from NeedlemanWunsch
+//! alignments, a guide tree built from them, and profiles aligned to each other along the
+//! tree, most similar sequences first.
+use needleman_wunsch::{AlignMatrix, Direction, NeedlemanWunsch, ResultEntry, traceback};
+
+/// Binary guide tree, the leaves are indices into the input sequences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuideTree {
+	Leaf(usize),
+	Node(Box<GuideTree>, Box<GuideTree>),
+}
+
+impl GuideTree {
+	/// Leaf indices from left to right.
+	pub fn leaves(&self) -> Vec<usize> {
+		match *self {
+			GuideTree::Leaf(i) => vec![i],
+			GuideTree::Node(ref l, ref r) => {
+				let mut res = l.leaves();
+				res.extend(r.leaves());
+				res
+			},
+		}
+	}
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TreeMethod {
+	Upgma,
+	NeighborJoining,
+}
+
+/// The aligned sequences in input order, all rows have the same length, None is a gap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipleAlignment<T> {
+	pub rows: Vec<Vec<Option<T>>>,
+}
+
+impl<T> MultipleAlignment<T> where T: Copy {
+	/// Number of columns.
+	pub fn len(&self) -> usize {
+		self.rows.first().map_or(0, |r| r.len())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Sum of the scores of all pairs of rows: similarity for two elements, the gap penalty
+	/// for an element against a gap, nothing for two gaps.
+	pub fn sum_of_pairs(&self, similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> i64 {
+		let mut score = 0;
+		for (i, a) in self.rows.iter().enumerate() {
+			for b in &self.rows[i + 1..] {
+				score += a.iter().zip(b).map(|(x, y)| pair_score(x, y, similarity, gap_penalty)).sum::<i64>();
+			}
+		}
+		score
+	}
+}
+
+fn pair_score<T>(a: &Option<T>, b: &Option<T>, similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> i64 {
+	match (a, b) {
+		(Some(x), Some(y)) => similarity(x, y),
+		(None, None) => 0,
+		_ => gap_penalty,
+	}
+}
+
+/// Distance of every pair of sequences: one minus the identity of their global alignment.
+pub fn distance_matrix<T>(seqs: &[Vec<T>], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Vec<Vec<f64>>
+	where T: Eq + Copy {
+	let n = seqs.len();
+	let mut dist = vec![vec![0.0; n]; n];
+	for i in 0..n {
+		for j in (i + 1)..n {
+			let alignment = seqs[i].align(&seqs[j], similarity, gap_penalty);
+			let d = if alignment.is_empty() { 0.0 } else { 1.0 - alignment.identity() };
+			dist[i][j] = d;
+			dist[j][i] = d;
+		}
+	}
+	dist
+}
+
+/// UPGMA: repeatedly join the two closest clusters, the distance to the joined cluster is the
+/// size weighted average of the distances to its parts.
+pub fn upgma(dist: &[Vec<f64>]) -> GuideTree {
+	assert!(!dist.is_empty(), "Need at least one sequence!");
+	let mut dist: Vec<Vec<f64>> = dist.to_vec();
+	let mut clusters: Vec<Option<(GuideTree, usize)>> = (0..dist.len()).map(|i| Some((GuideTree::Leaf(i), 1))).collect();
+	for _ in 1..dist.len() {
+		let (a, b) = closest_pair(&dist, &clusters, &|i, j| dist[i][j]);
+		let (tree_a, size_a) = clusters[a].take().unwrap();
+		let (tree_b, size_b) = clusters[b].take().unwrap();
+		let merged: Vec<f64> = dist[a].iter().zip(&dist[b])
+			.map(|(da, db)| (da * size_a as f64 + db * size_b as f64) / (size_a + size_b) as f64)
+			.collect();
+		for (row, &d) in dist.iter_mut().zip(&merged) {
+			row[a] = d;
+		}
+		dist[a] = merged;
+		clusters[a] = Some((GuideTree::Node(Box::new(tree_a), Box::new(tree_b)), size_a + size_b));
+	}
+	clusters.into_iter().flatten().next().unwrap().0
+}
+
+/// Neighbor joining. The tree is unrooted, it is rooted at the last join. With three clusters
+/// left the criterion is the same for every pair, those are joined by distance instead.
+pub fn neighbor_joining(dist: &[Vec<f64>]) -> GuideTree {
+	assert!(!dist.is_empty(), "Need at least one sequence!");
+	let mut dist: Vec<Vec<f64>> = dist.to_vec();
+	let mut clusters: Vec<Option<(GuideTree, usize)>> = (0..dist.len()).map(|i| Some((GuideTree::Leaf(i), 1))).collect();
+	for remaining in (2..(dist.len() + 1)).rev() {
+		let active: Vec<usize> = (0..dist.len()).filter(|&i| clusters[i].is_some()).collect();
+		let total: Vec<f64> = (0..dist.len()).map(|i| active.iter().map(|&k| dist[i][k]).sum()).collect();
+		let q = |i: usize, j: usize| {
+			if remaining <= 3 { dist[i][j] } else { (remaining - 2) as f64 * dist[i][j] - total[i] - total[j] }
+		};
+		let (a, b) = closest_pair(&dist, &clusters, &q);
+		let (tree_a, _) = clusters[a].take().unwrap();
+		let (tree_b, _) = clusters[b].take().unwrap();
+		let d_ab = dist[a][b];
+		for &k in &active {
+			let d = (dist[a][k] + dist[b][k] - d_ab) / 2.0;
+			dist[a][k] = d;
+			dist[k][a] = d;
+		}
+		dist[a][a] = 0.0;
+		clusters[a] = Some((GuideTree::Node(Box::new(tree_a), Box::new(tree_b)), 0));
+	}
+	clusters.into_iter().flatten().next().unwrap().0
+}
+
+// the pair of live clusters with the smallest criterion, the first one in index order on ties
+fn closest_pair<X>(dist: &[Vec<f64>], clusters: &[Option<X>], criterion: &dyn Fn(usize, usize) -> f64) -> (usize, usize) {
+	let mut best: Option<(f64, usize, usize)> = None;
+	for i in 0..dist.len() {
+		for j in (i + 1)..dist.len() {
+			if clusters[i].is_none() || clusters[j].is_none() {
+				continue;
+			}
+			let c = criterion(i, j);
+			if best.is_none_or(|b| c < b.0) {
+				best = Some((c, i, j));
+			}
+		}
+	}
+	let (_, i, j) = best.unwrap();
+	(i, j)
+}
+
+/// A group of aligned sequences: `ids` are the input indices of the rows.
+struct Profile<T> {
+	ids: Vec<usize>,
+	rows: Vec<Vec<Option<T>>>,
+}
+
+impl<T> Profile<T> where T: Copy {
+	fn len(&self) -> usize {
+		self.rows[0].len()
+	}
+
+	fn column(&self, i: usize) -> Vec<Option<T>> {
+		self.rows.iter().map(|r| r[i]).collect()
+	}
+}
+
+/// Align the sequences progressively along a guide tree built with the given method.
+pub fn progressive_align<T>(seqs: &[Vec<T>], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, method: TreeMethod) -> MultipleAlignment<T>
+	where T: Eq + Copy {
+	if seqs.is_empty() {
+		return MultipleAlignment { rows: Vec::new() };
+	}
+	let dist = distance_matrix(seqs, similarity, gap_penalty);
+	let tree = match method {
+		TreeMethod::Upgma => upgma(&dist),
+		TreeMethod::NeighborJoining => neighbor_joining(&dist),
+	};
+	align_along_tree(seqs, &tree, similarity, gap_penalty)
+}
+
+/// Align the sequences progressively along the given guide tree.
+pub fn align_along_tree<T>(seqs: &[Vec<T>], tree: &GuideTree, similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> MultipleAlignment<T>
+	where T: Eq + Copy {
+	let profile = build_profile(seqs, tree, similarity, gap_penalty);
+	let mut rows = vec![Vec::new(); seqs.len()];
+	for (id, row) in profile.ids.into_iter().zip(profile.rows) {
+		rows[id] = row;
+	}
+	MultipleAlignment { rows }
+}
+
+fn build_profile<T>(seqs: &[Vec<T>], tree: &GuideTree, similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Profile<T>
+	where T: Eq + Copy {
+	match *tree {
+		GuideTree::Leaf(i) => Profile {
+			ids: vec![i],
+			rows: vec![seqs[i].iter().map(|&c| Some(c)).collect()],
+		},
+		GuideTree::Node(ref l, ref r) => {
+			let left = build_profile(seqs, l, similarity, gap_penalty);
+			let right = build_profile(seqs, r, similarity, gap_penalty);
+			align_profiles(&left, &right, similarity, gap_penalty)
+		},
+	}
+}
+
+/// Needleman-Wunsch over the columns of two profiles. A pair of columns scores the sum of
+/// pairs between them, a column against a gap column scores the gap penalty for every
+/// element in it times the number of rows of the other profile. Gaps already in a profile
+/// stay where they are.
+fn align_profiles<T>(p1: &Profile<T>, p2: &Profile<T>, similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Profile<T>
+	where T: Eq + Copy {
+	let (n, m) = (p1.len(), p2.len());
+	let cols1: Vec<Vec<Option<T>>> = (0..n).map(|i| p1.column(i)).collect();
+	let cols2: Vec<Vec<Option<T>>> = (0..m).map(|j| p2.column(j)).collect();
+	let gap1: Vec<i64> = cols1.iter().map(|c| column_gap_score(c, p2.rows.len(), gap_penalty)).collect();
+	let gap2: Vec<i64> = cols2.iter().map(|c| column_gap_score(c, p1.rows.len(), gap_penalty)).collect();
+	let mut mat: AlignMatrix = vec![vec![ResultEntry::new(0, Direction::Undefined); m + 1]; n + 1];
+	for i in 1..(n + 1) {
+		mat[i][0] = ResultEntry::new(mat[i-1][0].score + gap1[i-1], Direction::Up);
+	}
+	for j in 1..(m + 1) {
+		mat[0][j] = ResultEntry::new(mat[0][j-1].score + gap2[j-1], Direction::Left);
+	}
+	for i in 1..(n + 1) {
+		for j in 1..(m + 1) {
+			let column_score: i64 = cols1[i-1].iter()
+				.map(|a| cols2[j-1].iter().map(|b| pair_score(a, b, similarity, gap_penalty)).sum::<i64>())
+				.sum();
+			let mut best = ResultEntry::new(mat[i-1][j-1].score + column_score, Direction::Match);
+			let up_score = mat[i-1][j].score + gap1[i-1];
+			if up_score > best.score {
+				best = ResultEntry::new(up_score, Direction::Up);
+			}
+			let left_score = mat[i][j-1].score + gap2[j-1];
+			if left_score > best.score {
+				best = ResultEntry::new(left_score, Direction::Left);
+			}
+			mat[i][j] = best;
+		}
+	}
+	// trace back over column indices, a None index is a gap column
+	let idx1: Vec<usize> = (0..n).collect();
+	let idx2: Vec<usize> = (0..m).collect();
+	let (path1, path2, _) = traceback(&mat, &idx1, &idx2, n, m);
+	let mut rows: Vec<Vec<Option<T>>> = Vec::with_capacity(p1.rows.len() + p2.rows.len());
+	for row in &p1.rows {
+		rows.push(path1.iter().map(|c| c.and_then(|i| row[i])).collect());
+	}
+	for row in &p2.rows {
+		rows.push(path2.iter().map(|c| c.and_then(|j| row[j])).collect());
+	}
+	let mut ids = p1.ids.clone();
+	ids.extend(&p2.ids);
+	Profile { ids, rows }
+}
+
+fn column_gap_score<T>(column: &[Option<T>], other_rows: usize, gap_penalty: i64) -> i64 {
+	column.iter().filter(|c| c.is_some()).count() as i64 * other_rows as i64 * gap_penalty
+}
+
+#[cfg(test)]
+mod test {
+	use super::{distance_matrix, upgma, neighbor_joining, progressive_align, align_along_tree, GuideTree, TreeMethod};
+	use needleman_wunsch::align_score;
+
+	fn similarity(a: &u8, b: &u8) -> i64 {
+		if a == b { 1 } else { -1 }
+	}
+
+	fn strip(row: &[Option<u8>]) -> Vec<u8> {
+		row.iter().filter_map(|&c| c).collect()
+	}
+
+	fn show(row: &[Option<u8>]) -> String {
+		row.iter().map(|c| c.map_or('-', |c| c as char)).collect()
+	}
+
+	fn leaf(i: usize) -> Box<GuideTree> {
+		Box::new(GuideTree::Leaf(i))
+	}
+
+	#[test]
+	fn test_guide_trees() {
+		let dist = vec![
+			vec![0.0, 0.1, 0.8, 0.9],
+			vec![0.1, 0.0, 0.7, 0.8],
+			vec![0.8, 0.7, 0.0, 0.2],
+			vec![0.9, 0.8, 0.2, 0.0],
+		];
+		let expected = GuideTree::Node(
+			Box::new(GuideTree::Node(leaf(0), leaf(1))),
+			Box::new(GuideTree::Node(leaf(2), leaf(3))),
+		);
+		assert_eq!(upgma(&dist), expected);
+		let nj = neighbor_joining(&dist);
+		let mut leaves = nj.leaves();
+		leaves.sort();
+		assert_eq!(leaves, vec![0, 1, 2, 3]);
+		// 0 and 1 are neighbors, so are 2 and 3, the closest pair of the last three is joined
+		match nj {
+			GuideTree::Node(ref l, ref r) => {
+				let mut sides = [l.leaves(), r.leaves()];
+				sides.iter_mut().for_each(|s| s.sort());
+				assert!(sides.contains(&vec![0, 1]) && sides.contains(&vec![2, 3]));
+			},
+			GuideTree::Leaf(_) => panic!("expected a node"),
+		}
+		assert_eq!(upgma(&[vec![0.0]]), GuideTree::Leaf(0));
+	}
+
+	#[test]
+	fn test_progressive_align() {
+		let seqs: Vec<Vec<u8>> = vec![b"GATTACA".to_vec(), b"GATACA".to_vec(), b"GATTACCA".to_vec(), b"GTTACA".to_vec()];
+		let dist = distance_matrix(&seqs, &similarity, -1);
+		assert_eq!(dist[0][0], 0.0);
+		assert_eq!(dist[0][1], dist[1][0]);
+		for &method in &[TreeMethod::Upgma, TreeMethod::NeighborJoining] {
+			let msa = progressive_align(&seqs, &similarity, -1, method);
+			assert_eq!(msa.rows.len(), seqs.len());
+			assert!(msa.rows.iter().all(|r| r.len() == msa.len()));
+			for (row, seq) in msa.rows.iter().zip(&seqs) {
+				assert_eq!(&strip(row), seq);
+			}
+			// no column is all gaps
+			assert!((0..msa.len()).all(|c| msa.rows.iter().any(|r| r[c].is_some())));
+		}
+	}
+
+	#[test]
+	fn test_two_sequences_match_pairwise() {
+		let seqs: Vec<Vec<u8>> = vec![b"ACGTTGCA".to_vec(), b"ACGTGCAA".to_vec()];
+		let tree = GuideTree::Node(leaf(0), leaf(1));
+		let msa = align_along_tree(&seqs, &tree, &similarity, -2);
+		assert_eq!(msa.sum_of_pairs(&similarity, -2), align_score(&seqs[0], &seqs[1], &similarity, -2));
+	}
+
+	#[test]
+	fn test_profile_keeps_gaps() {
+		let seqs: Vec<Vec<u8>> = vec![b"AAGG".to_vec(), b"AAAGG".to_vec(), b"AAGG".to_vec()];
+		let tree = GuideTree::Node(Box::new(GuideTree::Node(leaf(0), leaf(2))), leaf(1));
+		let msa = align_along_tree(&seqs, &tree, &similarity, -1);
+		assert_eq!(show(&msa.rows[0]), show(&msa.rows[2]));
+		assert_eq!(show(&msa.rows[1]), "AAAGG");
+		assert_eq!(msa.rows[0].iter().filter(|c| c.is_none()).count(), 1);
+		assert_eq!(msa.sum_of_pairs(&similarity, -1), 4 + 3 + 3);
+	}
+}
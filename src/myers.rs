@@ -0,0 +1,202 @@
+//! Myers' bit-parallel edit distance. A column of the Levenshtein matrix is kept as bit
+//! vectors of its vertical differences (+1 in `pv`, -1 in `mv`), so every text element costs
+//! a handful of word operations per 64 pattern elements instead of one cell per element.
+//! Patterns up to 64 elements fit in one word, longer ones are split into blocks of 64 and
+//! the horizontal difference at the bottom of each block is carried into the next.
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A pattern preprocessed for searching: for every element the bit mask of the positions
+/// where it occurs, one word per block.
+pub struct Myers<T> {
+	peq: HashMap<T, Vec<u64>>,
+	len: usize,
+	blocks: usize,
+}
+
+/// Vertical difference vectors of one block.
+#[derive(Copy, Clone)]
+struct Block {
+	pv: u64,
+	mv: u64,
+}
+
+/// Advance one block by one text element. `hin` is the horizontal difference entering at
+/// the top of the block, the difference leaving at the bottom (bit `high`) is returned.
+fn advance_block(block: &mut Block, eq: u64, hin: i64, high: u64) -> i64 {
+	let (pv, mv) = (block.pv, block.mv);
+	let eq = if hin < 0 { eq | 1 } else { eq };
+	let xv = eq | mv;
+	let xh = ((eq & pv).wrapping_add(pv) ^ pv) | eq;
+	let mut ph = mv | !(xh | pv);
+	let mut mh = pv & xh;
+	let hout = if ph & high != 0 { 1 } else if mh & high != 0 { -1 } else { 0 };
+	ph <<= 1;
+	mh <<= 1;
+	if hin < 0 {
+		mh |= 1;
+	} else if hin > 0 {
+		ph |= 1;
+	}
+	block.pv = mh | !(xv | ph);
+	block.mv = ph & xv;
+	hout
+}
+
+impl<T> Myers<T> where T: Eq + Hash + Copy {
+	pub fn new(pattern: &[T]) -> Myers<T> {
+		let blocks = pattern.len().div_ceil(64).max(1);
+		let mut peq: HashMap<T, Vec<u64>> = HashMap::new();
+		for (i, &c) in pattern.iter().enumerate() {
+			peq.entry(c).or_insert_with(|| vec![0; blocks])[i / 64] |= 1 << (i % 64);
+		}
+		Myers { peq, len: pattern.len(), blocks }
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Levenshtein distance between the pattern and the whole text.
+	pub fn distance(&self, text: &[T]) -> usize {
+		let mut res = self.len;
+		self.run(text, true, &mut |_, d| res = d);
+		res
+	}
+
+	/// Approximate search: every end position j (exclusive) such that the pattern matches some
+	/// substring of the text ending at j with at most `max_distance` edits, with the distance
+	/// of the best such substring.
+	pub fn find_all(&self, text: &[T], max_distance: usize) -> Vec<(usize, usize)> {
+		let mut res = Vec::new();
+		self.run(text, false, &mut |j, d| if d <= max_distance { res.push((j, d)) });
+		res
+	}
+
+	/// End position and distance of the best match in the text, the first one on ties.
+	pub fn find_best(&self, text: &[T]) -> (usize, usize) {
+		let mut best = (0, self.len);
+		self.run(text, false, &mut |j, d| if d < best.1 { best = (j, d) });
+		best
+	}
+
+	/// Feed the text through the bit vectors and report the score of the last row after every
+	/// element. In global mode the first row grows by one per element, in search mode it is
+	/// all zeros, i.e. a match may start anywhere.
+	fn run(&self, text: &[T], global: bool, report: &mut dyn FnMut(usize, usize)) {
+		if self.len == 0 {
+			for j in 1..(text.len() + 1) {
+				report(j, if global { j } else { 0 });
+			}
+			return;
+		}
+		let hin = if global { 1 } else { 0 };
+		let last_high = 1 << ((self.len - 1) % 64);
+		let mut score = self.len as i64;
+		if self.blocks == 1 {
+			// the common short pattern case, without the block loop
+			let mut block = Block { pv: !0, mv: 0 };
+			for (j, c) in text.iter().enumerate() {
+				let eq = self.peq.get(c).map_or(0, |e| e[0]);
+				score += advance_block(&mut block, eq, hin, last_high);
+				report(j + 1, score as usize);
+			}
+			return;
+		}
+		let mut blocks = vec![Block { pv: !0, mv: 0 }; self.blocks];
+		for (j, c) in text.iter().enumerate() {
+			let eq = self.peq.get(c);
+			let mut h = hin;
+			for (b, block) in blocks.iter_mut().enumerate() {
+				let high = if b + 1 == self.blocks { last_high } else { 1 << 63 };
+				h = advance_block(block, eq.map_or(0, |e| e[b]), h, high);
+			}
+			score += h;
+			report(j + 1, score as usize);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Myers;
+	use test_support::random_dna;
+	use edit_distance::levenshtein;
+	use needleman_wunsch::{AlignMode, NeedlemanWunsch};
+
+	fn unit_cost(a: &u8, b: &u8) -> i64 {
+		if a == b { 0 } else { -1 }
+	}
+
+	// best distance of the pattern against a substring of the text ending at j, from the
+	// alignment matrix with free leading gaps in the text
+	fn dp_search(pattern: &[u8], text: &[u8], j: usize) -> usize {
+		let mode = AlignMode::SemiGlobal { leading1: true, trailing1: false, leading2: false, trailing2: false };
+		(-text[..j].align_mode(pattern, &unit_cost, -1, mode).score) as usize
+	}
+
+	#[test]
+	fn test_distance() {
+		let pattern = b"kitten";
+		assert_eq!(Myers::new(&pattern[..]).distance(b"sitting"), 3);
+		assert_eq!(Myers::new(&b""[..]).distance(b"abc"), 3);
+		assert_eq!(Myers::new(&b"abc"[..]).distance(b""), 3);
+		// one word, exactly one block and several blocks
+		for &len in &[1, 17, 63, 64, 65, 128, 200] {
+			for seed in 0..3 {
+				let a = random_dna(len, seed);
+				let b = random_dna(len + seed as usize * 5, seed + 10);
+				assert_eq!(Myers::new(&a).distance(&b), levenshtein(&a, &b), "pattern length {}", len);
+			}
+		}
+		let chars: Vec<char> = "naïve café".chars().collect();
+		let other: Vec<char> = "naive cafe".chars().collect();
+		assert_eq!(Myers::new(&chars).distance(&other), 2);
+	}
+
+	#[test]
+	fn test_search() {
+		let myers = Myers::new(&b"GATTACA"[..]);
+		let text = b"TTTGATTACATTTGATCACATT";
+		let hits = myers.find_all(text, 1);
+		assert!(hits.contains(&(10, 0)));
+		assert!(hits.contains(&(20, 1)));
+		assert_eq!(myers.find_best(text), (10, 0));
+		for &len in &[5, 40, 64, 90] {
+			let pattern = random_dna(len, len as u64);
+			let text = random_dna(150, 99);
+			let myers = Myers::new(&pattern);
+			let all = myers.find_all(&text, len);
+			assert_eq!(all.len(), text.len());
+			for &(j, d) in &all {
+				assert_eq!(d, dp_search(&pattern, &text, j), "pattern length {}, end {}", len, j);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod bench {
+	use test::Bencher;
+	use super::Myers;
+	use test_support::random_dna;
+	use edit_distance::levenshtein;
+
+	const LEN: usize = 1000;
+
+	#[bench]
+	fn bench_myers_distance(b: &mut Bencher) {
+		let (vec1, vec2) = (random_dna(LEN, 1), random_dna(LEN, 2));
+		b.iter(|| Myers::new(&vec1).distance(&vec2));
+	}
+
+	#[bench]
+	fn bench_dp_distance(b: &mut Bencher) {
+		let (vec1, vec2) = (random_dna(LEN, 1), random_dna(LEN, 2));
+		b.iter(|| levenshtein(&vec1, &vec2));
+	}
+}
@@ -1,6 +1,7 @@
 use std::collections::BTreeSet;
-use point::Point;
+use point::{Point, sort_points};
 use triangle::Triangle;
+use coordinate::Coordinate;
 
 #[macro_export]
 macro_rules! btreeset {
@@ -11,11 +12,11 @@ macro_rules! btreeset {
     }}
 }
 
-pub fn convex_hull(points: &BTreeSet<Point>) -> BTreeSet<Point> {
+pub fn convex_hull<T>(points: &BTreeSet<Point<T>>) -> BTreeSet<Point<T>> where T: Coordinate {
     // you must have at least 3 points, otherwise there is no hull
     assert!(points.len() >= 3);
     // Remove just one point from the set
-    let minus_one = |p: &Point| {
+    let minus_one = |p: &Point<T>| {
         let mut subset = points.clone();
         subset.remove(p);
         subset
@@ -39,17 +40,8 @@ pub fn convex_hull(points: &BTreeSet<Point>) -> BTreeSet<Point> {
     }
     // set of points that are not internal
     let mut hull: Vec<_> = points.difference(&p_internal_set).cloned().collect();
-    // sort by coordinates so that the first point is the leftmost
-    hull.sort();
-    let head = hull[0];
-
-    // sort by the angle with the first point
-    // when that is equal, sort by distance to head
-    hull.sort_by(|a, b| {
-        let angle_a = head.angle(&a);
-        let angle_b = head.angle(&b);
-        angle_a.partial_cmp(&angle_b).unwrap()
-    });
+    // sort by the angle with the leftmost point
+    sort_points(&mut hull);
     hull.into_iter().collect()
 }
 
@@ -91,4 +83,15 @@ mod test {
         assert_eq!(hull, hull_should_be);
     }
 
+    #[test]
+    fn test_convex_hull_naive_integer() {
+        let points: BTreeSet<_> = (0..4)
+            .flat_map(|i| (0..4).map(move |j| Point::from_coords(i as i64, j as i64)))
+            .collect();
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 12);
+        assert!(!hull.contains(&Point::from_coords(1, 1)));
+        assert!(!hull.contains(&Point::from_coords(2, 2)));
+    }
+
 }
@@ -6,104 +6,528 @@ pub enum Direction {
 	Undefined, 
 }
 
+/// A set of directions, one bit per move. Undefined is the empty set.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct Directions(u8);
+
+impl Directions {
+	fn bit(d: Direction) -> u8 {
+		match d {
+			Direction::Match => 1,
+			Direction::Up => 2,
+			Direction::Left => 4,
+			Direction::Undefined => 0,
+		}
+	}
+
+	pub fn contains(&self, d: Direction) -> bool {
+		d != Direction::Undefined && self.0 & Directions::bit(d) != 0
+	}
+
+	pub fn insert(&mut self, d: Direction) {
+		self.0 |= Directions::bit(d);
+	}
+
+	pub fn remove(&mut self, d: Direction) {
+		self.0 &= !Directions::bit(d);
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.count_ones() as usize
+	}
+}
+
+impl From<Direction> for Directions {
+	fn from(d: Direction) -> Directions {
+		Directions(Directions::bit(d))
+	}
+}
+
+/// Order in which equally scoring moves are preferred when a single alignment is built.
+/// The default, Match over Up over Left, puts gaps in the first sequence as late as possible.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TieBreak([Direction; 3]);
+
+impl Default for TieBreak {
+	fn default() -> TieBreak {
+		TieBreak([Direction::Match, Direction::Up, Direction::Left])
+	}
+}
+
+impl TieBreak {
+	/// The policy preferring the moves in the given order, None unless the order is a
+	/// permutation of Match, Up and Left: a move left out could never be taken and the
+	/// traceback would stop early.
+	pub fn new(order: [Direction; 3]) -> Option<TieBreak> {
+		let all = [Direction::Match, Direction::Up, Direction::Left];
+		if all.iter().all(|d| order.contains(d)) {
+			Some(TieBreak(order))
+		} else {
+			None
+		}
+	}
+
+	pub fn order(&self) -> [Direction; 3] {
+		self.0
+	}
+
+	/// The most preferred of the given directions, Undefined for the empty set.
+	pub fn choose(&self, directions: Directions) -> Direction {
+		*self.0.iter().find(|d| directions.contains(**d)).unwrap_or(&Direction::Undefined)
+	}
+}
+
+/// Which end gaps are scored. A free leading or trailing gap of a sequence lets the
+/// beginning or the end of it hang over the other sequence without penalty.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum AlignMode {
+	/// Every gap is scored, both sequences are aligned end to end.
+	#[default]
+	Global,
+	/// `leading1` / `trailing1`: a prefix / suffix of the first sequence may stay unaligned
+	/// for free, likewise for the second sequence.
+	SemiGlobal {
+		leading1: bool,
+		trailing1: bool,
+		leading2: bool,
+		trailing2: bool,
+	},
+}
+
+impl AlignMode {
+	/// All end gaps free: finds overlaps such as a suffix of one sequence with a prefix of the other.
+	pub fn overlap() -> AlignMode {
+		AlignMode::SemiGlobal { leading1: true, trailing1: true, leading2: true, trailing2: true }
+	}
+
+	/// The second sequence aligned end to end somewhere inside the first, e.g. a read against
+	/// a reference.
+	pub fn glocal() -> AlignMode {
+		AlignMode::SemiGlobal { leading1: true, trailing1: true, leading2: false, trailing2: false }
+	}
+
+	fn leading(&self) -> (bool, bool) {
+		match *self {
+			AlignMode::Global => (false, false),
+			AlignMode::SemiGlobal { leading1, leading2, .. } => (leading1, leading2),
+		}
+	}
+
+	fn trailing(&self) -> (bool, bool) {
+		match *self {
+			AlignMode::Global => (false, false),
+			AlignMode::SemiGlobal { trailing1, trailing2, .. } => (trailing1, trailing2),
+		}
+	}
+}
+
+/// `direction` is the move the traceback takes, `directions` holds every move that reaches
+/// the optimal score of this cell.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct ResultEntry {
 	pub score: i64,
 	pub direction: Direction,
+	pub directions: Directions,
 }
 
 impl ResultEntry {
-	fn new(s: i64, d: Direction) -> ResultEntry {
+	pub fn new(s: i64, d: Direction) -> ResultEntry {
 		ResultEntry {
 			score: s, 
 			direction: d,
+			directions: Directions::from(d),
 		}
 	}
 }
 
+use std::borrow::Cow;
+
+use alignment::Alignment;
+
+/// Global alignment of two slices. Elements that are Copy end up in the alignment by value,
+/// any other element type can be aligned by reference with the `_ref` methods, which need
+/// neither Copy nor Clone.
 pub trait NeedlemanWunsch<T> {
-	fn align(&mut self, vec: &mut Vec<T>, similarity: &Fn(&T, &T) -> i64, gap_penalty: i64) -> (Vec<Option<T>>, Vec<Option<T>>);
+	fn align(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Alignment<T> where T: Copy {
+		self.align_tie_break(vec, similarity, gap_penalty, TieBreak::default())
+	}
+	fn align_tie_break(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, tie_break: TieBreak) -> Alignment<T> where T: Copy;
+	/// Align with free end gaps as given by the mode. The overhanging ends are part of the
+	/// columns as gaps but do not count towards the score.
+	fn align_mode(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, mode: AlignMode) -> Alignment<T> where T: Copy;
+	/// Like `align`, with references to the elements in the alignment.
+	fn align_ref<'a>(&'a self, vec: &'a [T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Alignment<&'a T> {
+		self.align_ref_mode(vec, similarity, gap_penalty, TieBreak::default(), AlignMode::Global)
+	}
+	/// Like `align_tie_break` and `align_mode` together, with references to the elements in
+	/// the alignment.
+	fn align_ref_mode<'a>(&'a self, vec: &'a [T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, tie_break: TieBreak, mode: AlignMode) -> Alignment<&'a T>;
+	/// Iterate over the optimal alignments, at most `limit` of them. The first one is the
+	/// alignment `align` returns.
+	fn align_all<'a>(&'a self, vec: &'a [T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, limit: usize) -> OptimalAlignments<'a, T> where T: Copy;
+	/// Like `align_all`, with references to the elements in the alignments.
+	fn align_all_ref<'a>(&'a self, vec: &'a [T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, limit: usize) -> OptimalAlignments<'a, &'a T>;
 }
 
 
-impl<T> NeedlemanWunsch<T> for Vec<T> where T: Eq + Copy + Clone {
-	fn align(&mut self, vec: &mut Vec<T>, similarity: &Fn(&T, &T) -> i64, gap_penalty: i64) -> (Vec<Option<T>>, Vec<Option<T>>) {
-		let mut mat = init_align_matrix(self.len() , vec.len());
-		fill_align_matrix(&mut mat, self, vec, similarity, gap_penalty);
-		let mat = &mat; // no need to mutate mat any more. 
-		let mut vec1: Vec<Option<T>> = Vec::new(); 
-		let mut vec2: Vec<Option<T>> = Vec::new(); 
-		// the dimension of the alignment matrix is one larger than vec1.len by vec2.len
-		let mut i = self.len(); 
-		let mut j = vec.len(); 
+impl<T> NeedlemanWunsch<T> for [T] where T: Eq {
+	fn align_tie_break(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, tie_break: TieBreak) -> Alignment<T> where T: Copy {
+		align_with_mode(self, vec, similarity, gap_penalty, tie_break, AlignMode::Global)
+	}
+
+	fn align_mode(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, mode: AlignMode) -> Alignment<T> where T: Copy {
+		align_with_mode(self, vec, similarity, gap_penalty, TieBreak::default(), mode)
+	}
+
+	fn align_ref_mode<'a>(&'a self, vec: &'a [T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, tie_break: TieBreak, mode: AlignMode) -> Alignment<&'a T> {
+		// the references are Copy, so the same code aligns them
+		let refs1: Vec<&T> = self.iter().collect();
+		let refs2: Vec<&T> = vec.iter().collect();
+		align_with_mode(&refs1, &refs2, &|a: &&T, b: &&T| similarity(a, b), gap_penalty, tie_break, mode)
+	}
+
+	fn align_all<'a>(&'a self, vec: &'a [T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, limit: usize) -> OptimalAlignments<'a, T> where T: Copy {
+		optimal_alignments(Cow::Borrowed(self), Cow::Borrowed(vec), similarity, gap_penalty, limit)
+	}
+
+	fn align_all_ref<'a>(&'a self, vec: &'a [T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, limit: usize) -> OptimalAlignments<'a, &'a T> {
+		let refs1: Cow<[&T]> = Cow::Owned(self.iter().collect());
+		let refs2: Cow<[&T]> = Cow::Owned(vec.iter().collect());
+		optimal_alignments(refs1, refs2, &|a: &&T, b: &&T| similarity(a, b), gap_penalty, limit)
+	}
+}
+
+fn optimal_alignments<'a, T>(seq1: Cow<'a, [T]>, seq2: Cow<'a, [T]>, similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, limit: usize) -> OptimalAlignments<'a, T>
+	where T: Clone {
+	let (n, m) = (seq1.len(), seq2.len());
+	let mut mat = init_align_matrix(n, m, gap_penalty, AlignMode::Global);
+	fill_align_matrix(&mut mat, &seq1, &seq2, similarity, gap_penalty, TieBreak::default());
+	let last = mat[n][m].directions;
+	OptimalAlignments {
+		mat,
+		seq1,
+		seq2,
+		stack: vec![(n, m, last)],
+		path: Vec::new(),
+		remaining: limit,
+	}
+}
+
+fn align_with_mode<T>(seq1: &[T], seq2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, tie_break: TieBreak, mode: AlignMode) -> Alignment<T>
+	where T: Eq + Copy {
+	let mut mat = init_align_matrix(seq1.len(), seq2.len(), gap_penalty, mode);
+	fill_align_matrix(&mut mat, seq1, seq2, similarity, gap_penalty, tie_break);
+	let (i, j) = traceback_start(&mat, mode);
+	let (mut vec1, mut vec2, _) = traceback(&mat, seq1, seq2, i, j);
+	// the unaligned suffix, at most one of the two is non-empty
+	for &c in &seq1[i..] {
+		vec1.push(Some(c));
+		vec2.push(None);
+	}
+	for &c in &seq2[j..] {
+		vec1.push(None);
+		vec2.push(Some(c));
+	}
+	Alignment::from_pair(vec1, vec2, mat[i][j].score)
+}
+
+/// The cell the traceback starts from: the last one for a global alignment, otherwise the
+/// best cell of the last column if the end of the first sequence is free and of the last row
+/// if the end of the second one is. Ties go to the last cell, then to the longer alignment.
+pub(crate) fn traceback_start(mat: &AlignMatrix, mode: AlignMode) -> (usize, usize) {
+	let n = mat.len() - 1;
+	let m = mat[0].len() - 1;
+	let (free1, free2) = mode.trailing();
+	let mut best = (n, m);
+	if free1 {
+		for i in (0..n).rev() {
+			if mat[i][m].score > mat[best.0][best.1].score {
+				best = (i, m);
+			}
+		}
+	}
+	if free2 {
+		for j in (0..m).rev() {
+			if mat[n][j].score > mat[best.0][best.1].score {
+				best = (n, j);
+			}
+		}
+	}
+	best
+}
+
+/// Depth first enumeration of all paths through the direction sets from the last cell back
+/// to the first one. Every cell on the current path is on the stack together with the moves
+/// out of it that are not explored yet, `path` holds one column per move taken. The sequences
+/// are borrowed, or owned when they are the references to the elements of borrowed ones.
+pub struct OptimalAlignments<'a, T: 'a + Clone> {
+	mat: AlignMatrix,
+	seq1: Cow<'a, [T]>,
+	seq2: Cow<'a, [T]>,
+	stack: Vec<(usize, usize, Directions)>,
+	path: Vec<(Option<T>, Option<T>)>,
+	remaining: usize,
+}
+
+impl<'a, T> Iterator for OptimalAlignments<'a, T> where T: Eq + Copy {
+	type Item = Alignment<T>;
+
+	fn next(&mut self) -> Option<Alignment<T>> {
+		if self.remaining == 0 {
+			return None;
+		}
 		loop {
-			match mat[i][j].direction {
-				Direction::Match => {
-					vec1.insert(0, Some(self[i-1]));
-					vec2.insert(0, Some(vec[j-1]));
-					i -= 1; 
-					j -= 1; 
-				}, 
-				Direction::Left => {
-					vec1.insert(0, None);
-					// the dimension of the alignment matrix is one larger than vec1.len by vec2.len
-					vec2.insert(0, Some(vec[j-1]));
-					j -= 1; 
-				}, 
-				Direction::Up => {
-					// the dimension of the alignment matrix is one larger than vec1.len by vec2.len
-					vec1.insert(0, Some(self[i-1]));
-					vec2.insert(0, None);
-					i -= 1;
-				}, 
-				Direction::Undefined => {
-					break;
-				}
+			let top = self.stack.last_mut()?;
+			if top.0 == 0 && top.1 == 0 {
+				break;
+			}
+			let (i, j) = (top.0, top.1);
+			let d = TieBreak::default().choose(top.2);
+			if d == Direction::Undefined {
+				// every move out of this cell is explored, backtrack
+				self.stack.pop();
+				self.path.pop();
+				continue;
 			}
+			top.2.remove(d);
+			let (ni, nj, column) = match d {
+				Direction::Match => (i - 1, j - 1, (Some(self.seq1[i-1]), Some(self.seq2[j-1]))),
+				Direction::Up => (i - 1, j, (Some(self.seq1[i-1]), None)),
+				_ => (i, j - 1, (None, Some(self.seq2[j-1]))),
+			};
+			self.path.push(column);
+			self.stack.push((ni, nj, self.mat[ni][nj].directions));
 		}
-		return (vec1, vec2);
+		// reached the first cell: the path read backwards is an optimal alignment
+		let (seq1, seq2): (Vec<_>, Vec<_>) = self.path.iter().rev().cloned().unzip();
+		self.stack.pop();
+		self.path.pop();
+		self.remaining -= 1;
+		let (n, m) = (self.seq1.len(), self.seq2.len());
+		Some(Alignment::from_pair(seq1, seq2, self.mat[n][m].score))
 	}
 }
 
+/// Both aligned sequences and the position in the matrix where the traceback stopped.
+pub(crate) type Traceback<T> = (Vec<Option<T>>, Vec<Option<T>>, (usize, usize));
+
+/// Follow the directions from cell (i, j) back to the first cell with an undefined direction.
+pub(crate) fn traceback<T>(mat: &AlignMatrix, seq1: &[T], seq2: &[T], i: usize, j: usize) -> Traceback<T>
+	where T: Copy {
+	let mut vec1: Vec<Option<T>> = Vec::new(); 
+	let mut vec2: Vec<Option<T>> = Vec::new(); 
+	let mut i = i; 
+	let mut j = j; 
+	// the dimension of the alignment matrix is one larger than seq1.len by seq2.len
+	loop {
+		match mat[i][j].direction {
+			Direction::Match => {
+				vec1.push(Some(seq1[i-1]));
+				vec2.push(Some(seq2[j-1]));
+				i -= 1; 
+				j -= 1; 
+			}, 
+			Direction::Left => {
+				vec1.push(None);
+				vec2.push(Some(seq2[j-1]));
+				j -= 1; 
+			}, 
+			Direction::Up => {
+				vec1.push(Some(seq1[i-1]));
+				vec2.push(None);
+				i -= 1;
+			}, 
+			Direction::Undefined => {
+				break;
+			}
+		}
+	}
+	vec1.reverse();
+	vec2.reverse();
+	(vec1, vec2, (i, j))
+}
+
 #[test]
 fn test_align() {
 	fn similarity(c1: &char, c2: &char) -> i64 {
 		if c1 == c2 { 1 } else { -1 }
 	}
-	let mut vec1: Vec<_> = "what".chars().collect();
-	let mut vec2: Vec<_> = "white".chars().collect();
-	let res = vec1.align(&mut vec2, &similarity, -1);
+	let vec1: Vec<_> = "what".chars().collect();
+	let vec2: Vec<_> = "white".chars().collect();
+	let res = vec1.align(&vec2, &similarity, -1);
 	let res_should_be = (
 		vec![Some('w'), Some('h'), Some('a'), Some('t'), None], 
 		vec![Some('w'), Some('h'), Some('i'), Some('t'), Some('e')], 
 	);
-	assert_eq!(res, res_should_be);
-	let mut vec1: Vec<_> = "ab".chars().collect();
-	let mut vec2: Vec<_> = "aeb".chars().collect();
-	let res = vec1.align(&mut vec2, &similarity, -1);
+	assert_eq!(res.to_pair(), res_should_be);
+	assert_eq!(res.score, 1);
+	assert_eq!(format!("{}", res), "what-\n|| |\nwhite");
+	let vec1: Vec<_> = "ab".chars().collect();
+	let vec2: Vec<_> = "aeb".chars().collect();
+	let res = vec1.align(&vec2, &similarity, -1);
 	let res_should_be = (
 		vec![Some('a'), None, Some('b')], 
 		vec![Some('a'), Some('e'), Some('b')], 
 	);
-	assert_eq!(res, res_should_be);
+	assert_eq!(res.to_pair(), res_should_be);
+	assert_eq!(res.score, 1);
 }
 
-type AlignMatrix = Vec<Vec<ResultEntry>>;
+#[test]
+fn test_tie_break_new() {
+	use self::Direction::{Match, Up, Left, Undefined};
+	assert_eq!(TieBreak::new([Match, Up, Left]), Some(TieBreak::default()));
+	assert_eq!(TieBreak::new([Left, Up, Match]).map(|t| t.order()), Some([Left, Up, Match]));
+	// a move that is left out or given twice could end the traceback before the first cell
+	assert_eq!(TieBreak::new([Match, Match, Match]), None);
+	assert_eq!(TieBreak::new([Match, Up, Up]), None);
+	assert_eq!(TieBreak::new([Match, Up, Undefined]), None);
+}
+
+#[test]
+fn test_align_all() {
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 1 } else { -1 }
+	}
+	fn show(v: &[Option<char>]) -> String {
+		v.iter().map(|c| c.unwrap_or('-')).collect()
+	}
+	let vec1: Vec<_> = "ab".chars().collect();
+	let vec2: Vec<_> = "aab".chars().collect();
+	let all: Vec<_> = vec1.align_all(&vec2, &similarity, -1, 10).collect();
+	let shown: Vec<_> = all.iter().map(|a| (show(&a.seq1()), show(&a.seq2()))).collect();
+	assert_eq!(shown, vec![
+		("-ab".to_string(), "aab".to_string()),
+		("a-b".to_string(), "aab".to_string()),
+	]);
+	assert!(all.iter().all(|a| a.score == 1 && a.linear_score(&similarity, -1) == 1));
+	assert_eq!(all[0], vec1.align(&vec2, &similarity, -1));
+	// the policy decides which of the two the single result is
+	let left_first = TieBreak::new([Direction::Left, Direction::Match, Direction::Up]).unwrap();
+	assert_eq!(vec1.align_tie_break(&vec2, &similarity, -1, left_first), all[1]);
+	assert_eq!(vec1.align_all(&vec2, &similarity, -1, 1).count(), 1);
+	assert_eq!(vec1.align_all(&vec2, &similarity, -1, 0).count(), 0);
+	// aaaa vs aa: any two of the four can be matched
+	let vec1: Vec<_> = "aaaa".chars().collect();
+	let vec2: Vec<_> = "aa".chars().collect();
+	let all: Vec<_> = vec1.align_all(&vec2, &similarity, -1, 100).collect();
+	assert_eq!(all.len(), 6);
+	assert!(all.iter().all(|a| a.score == 0 && a.matches() == 2));
+	let empty: Vec<char> = Vec::new();
+	assert_eq!(empty.align_all(&[], &similarity, -1, 5).count(), 1);
+}
+
+#[test]
+fn test_align_mode() {
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 1 } else { -1 }
+	}
+	fn show(v: &[Option<char>]) -> String {
+		v.iter().map(|c| c.unwrap_or('-')).collect()
+	}
+	// read inside a reference
+	let reference: Vec<_> = "ttttgattacatttt".chars().collect();
+	let read: Vec<_> = "gattaca".chars().collect();
+	let res = reference.align_mode(&read, &similarity, -1, AlignMode::glocal());
+	assert_eq!(res.score, 7);
+	assert_eq!(show(&res.seq1()), "ttttgattacatttt");
+	assert_eq!(show(&res.seq2()), "----gattaca----");
+	assert_eq!(reference.align(&read, &similarity, -1).score, -1);
+	// the ends of the read are not free, so it is not clipped
+	let read: Vec<_> = "aagattaca".chars().collect();
+	let res = reference.align_mode(&read, &similarity, -1, AlignMode::glocal());
+	assert_eq!(show(&res.seq2()).replace('-', ""), "aagattaca");
+	// suffix of the first overlapping a prefix of the second
+	let vec1: Vec<_> = "xxxxacgt".chars().collect();
+	let vec2: Vec<_> = "acgtyyy".chars().collect();
+	let res = vec1.align_mode(&vec2, &similarity, -1, AlignMode::overlap());
+	assert_eq!(res.score, 4);
+	assert_eq!(show(&res.seq1()), "xxxxacgt---");
+	assert_eq!(show(&res.seq2()), "----acgtyyy");
+	// only the trailing end of the second sequence is free
+	let mode = AlignMode::SemiGlobal { leading1: false, trailing1: false, leading2: false, trailing2: true };
+	let res = vec2.align_mode(&vec1, &similarity, -1, mode);
+	assert_eq!(res.score, vec2.align(&vec1, &similarity, -1).score.max(res.score));
+	assert_eq!(res.seq1().iter().filter(|c| c.is_some()).count(), vec2.len());
+	assert_eq!(res.seq2().iter().filter(|c| c.is_some()).count(), vec1.len());
+	let empty: Vec<char> = Vec::new();
+	let res = vec1.align_mode(&empty, &similarity, -1, AlignMode::overlap());
+	assert_eq!(res.score, 0);
+	assert_eq!(res.len(), vec1.len());
+}
+
+#[test]
+fn test_align_ref() {
+	// String is not Copy, the alignment borrows the words instead of cloning them
+	fn similarity(w1: &String, w2: &String) -> i64 {
+		if w1 == w2 { 2 } else { -1 }
+	}
+	let words1: Vec<String> = "the quick brown fox".split(' ').map(String::from).collect();
+	let words2: Vec<String> = "the brown fox jumps".split(' ').map(String::from).collect();
+	let res = words1.align_ref(&words2, &similarity, -1);
+	assert_eq!(res.score, 4);
+	assert_eq!(res.seq1(), vec![Some(&words1[0]), Some(&words1[1]), Some(&words1[2]), Some(&words1[3]), None]);
+	assert_eq!(res.seq2(), vec![Some(&words2[0]), None, Some(&words2[1]), Some(&words2[2]), Some(&words2[3])]);
+	// for Copy elements it is the alignment by value, borrowed
+	let chars1: Vec<_> = "what".chars().collect();
+	let chars2: Vec<_> = "white".chars().collect();
+	let by_ref = chars1.align_ref(&chars2, &|a, b| if a == b { 1 } else { -1 }, -1);
+	let by_value = chars1[..].align(&chars2, &|a, b| if a == b { 1 } else { -1 }, -1);
+	assert_eq!(by_ref.score, by_value.score);
+	assert_eq!(by_ref.seq1().iter().map(|c| c.cloned()).collect::<Vec<_>>(), by_value.seq1());
+}
+
+#[test]
+fn test_align_ref_mode_and_all_ref() {
+	fn similarity(w1: &String, w2: &String) -> i64 {
+		if w1 == w2 { 1 } else { -1 }
+	}
+	let words = |s: &str| -> Vec<String> { s.split(' ').map(String::from).collect() };
+	let (words1, words2) = (words("a b c d e"), words("x b c y"));
+	// the overhangs are free in overlap mode, same columns as by value on the indices
+	let res = words1.align_ref_mode(&words2, &similarity, -1, TieBreak::default(), AlignMode::overlap());
+	let idx1: Vec<usize> = (0..words1.len()).collect();
+	let idx2: Vec<usize> = (0..words2.len()).collect();
+	let by_value = idx1.align_mode(&idx2, &|&i, &j| similarity(&words1[i], &words2[j]), -1, AlignMode::overlap());
+	assert_eq!(res.score, by_value.score);
+	assert_eq!(res.seq1(), by_value.seq1().iter().map(|c| c.map(|i| &words1[i])).collect::<Vec<_>>());
+	assert_eq!(res.seq2(), by_value.seq2().iter().map(|c| c.map(|j| &words2[j])).collect::<Vec<_>>());
+	// the optimal alignments by reference are those by value on the indices
+	let all: Vec<_> = words1.align_all_ref(&words2, &similarity, -1, 10).collect();
+	let all_by_value: Vec<_> = idx1.align_all(&idx2, &|&i, &j| similarity(&words1[i], &words2[j]), -1, 10).collect();
+	assert!(all.len() > 1);
+	assert_eq!(all.len(), all_by_value.len());
+	for (a, b) in all.iter().zip(&all_by_value) {
+		assert_eq!(a.score, b.score);
+		assert_eq!(a.seq1(), b.seq1().iter().map(|c| c.map(|i| &words1[i])).collect::<Vec<_>>());
+	}
+}
+
+pub type AlignMatrix = Vec<Vec<ResultEntry>>;
 
 /// len1: Length of the first vector, or number of rows for the alignment matrix.
 /// len2: Length of the second vector, or number of cols for the alignment matrix.
-fn init_align_matrix(len1: usize, len2: usize) -> AlignMatrix {
+/// gap_penalty: Score of a single gap, the first row and col are filled with its multiples.
+/// mode: A free leading gap of a sequence sets its boundary to zero instead.
+pub(crate) fn init_align_matrix(len1: usize, len2: usize, gap_penalty: i64, mode: AlignMode) -> AlignMatrix {
+	let (free1, free2) = mode.leading();
+	let row_gap = if free2 { 0 } else { gap_penalty };
+	let col_gap = if free1 { 0 } else { gap_penalty };
 	let len1 = len1 + 1; 
 	let len2 = len2 + 1; 
 	// starting point has undefined direction
 	let mut mat: AlignMatrix = vec![vec![ResultEntry::new(0, Direction::Undefined); len2]; len1];
 	// first row all go left
-	for i in 1..len2 {
-		mat[0][i] = ResultEntry::new(-(i as i64), Direction::Left);
+	for (j, entry) in mat[0].iter_mut().enumerate().skip(1) {
+		*entry = ResultEntry::new(j as i64 * row_gap, Direction::Left);
 	}
 	// first col all go up
-	for i in 1..len1 {
-		mat[i][0] = ResultEntry::new(-(i as i64), Direction::Up);
+	for (i, row) in mat.iter_mut().enumerate().skip(1) {
+		row[0] = ResultEntry::new(i as i64 * col_gap, Direction::Up);
 	}
 	mat[0][0] = ResultEntry::new(0, Direction::Undefined);
 	mat
@@ -111,13 +535,27 @@ fn init_align_matrix(len1: usize, len2: usize) -> AlignMatrix {
 
 #[test]
 fn test_align_mat() {
-	let m = init_align_matrix(2, 3); 
+	let m = init_align_matrix(2, 3, -1, AlignMode::Global); 
 	let m_should_be = vec![
 		vec![ResultEntry::new( 0, Direction::Undefined), ResultEntry::new(-1, Direction::Left),      ResultEntry::new(-2, Direction::Left),      ResultEntry::new(-3, Direction::Left)],
 		vec![ResultEntry::new(-1, Direction::Up),        ResultEntry::new( 0, Direction::Undefined), ResultEntry::new( 0, Direction::Undefined), ResultEntry::new( 0, Direction::Undefined)],
 		vec![ResultEntry::new(-2, Direction::Up),        ResultEntry::new( 0, Direction::Undefined), ResultEntry::new( 0, Direction::Undefined), ResultEntry::new( 0, Direction::Undefined)],
 	];
 	assert_eq!(m, m_should_be);
+	let m = init_align_matrix(2, 1, -3, AlignMode::Global); 
+	let m_should_be = vec![
+		vec![ResultEntry::new( 0, Direction::Undefined), ResultEntry::new(-3, Direction::Left)],
+		vec![ResultEntry::new(-3, Direction::Up),        ResultEntry::new( 0, Direction::Undefined)],
+		vec![ResultEntry::new(-6, Direction::Up),        ResultEntry::new( 0, Direction::Undefined)],
+	];
+	assert_eq!(m, m_should_be);
+	let m = init_align_matrix(2, 1, -3, AlignMode::SemiGlobal { leading1: true, trailing1: false, leading2: false, trailing2: false });
+	let m_should_be = vec![
+		vec![ResultEntry::new( 0, Direction::Undefined), ResultEntry::new(-3, Direction::Left)],
+		vec![ResultEntry::new( 0, Direction::Up),        ResultEntry::new( 0, Direction::Undefined)],
+		vec![ResultEntry::new( 0, Direction::Up),        ResultEntry::new( 0, Direction::Undefined)],
+	];
+	assert_eq!(m, m_should_be);
 }
 
 #[test]
@@ -129,10 +567,10 @@ fn test_fill_mat() {
 				-1
 			}
 		}
-		let mut vec1: Vec<_>  = "ab".chars().collect();
-		let mut vec2: Vec<_>  = "aeb".chars().collect();
-		let mut mat = init_align_matrix(vec1.len(), vec2.len());
-		fill_align_matrix(&mut mat, &mut vec1, &mut vec2, &similarity, -1);
+		let vec1: Vec<_>  = "ab".chars().collect();
+		let vec2: Vec<_>  = "aeb".chars().collect();
+		let mut mat = init_align_matrix(vec1.len(), vec2.len(), -1, AlignMode::Global);
+		fill_align_matrix(&mut mat, &vec1, &vec2, &similarity, -1, TieBreak::default());
 		use self::Direction::Undefined as ud;
 		use self::Direction::Left as lt; 
 		use self::Direction::Up as up; 
@@ -145,89 +583,177 @@ fn test_fill_mat() {
 		assert_eq!(mat, res_should_be);
 }
 
-fn fill_align_matrix<T>(mat: &mut AlignMatrix, vec1: &mut Vec<T>, vec2: &mut Vec<T>, similarity: &Fn(&T, &T) -> i64, gap_penalty: i64) {
+/// Fill the matrix row by row, every cell only depends on the cells above, to the left and
+/// diagonally up-left, which are all computed by then. All moves reaching the best score are
+/// recorded, the tie-breaking policy picks the one the traceback follows.
+pub(crate) fn fill_align_matrix<T>(mat: &mut AlignMatrix, vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64, tie_break: TieBreak) {
 	assert_eq!(mat.len(), vec1.len() + 1);
 	assert_eq!(mat[0].len(), vec2.len() + 1);
-	
-	fn get_score_set_direction<U>(mat: &mut AlignMatrix, vec1: &Vec<U>, vec2: &Vec<U>, similarity: &Fn(&U, &U) -> i64, gap_penalty: i64, i: usize, j: usize) -> i64 {
-		if i == 0 || j == 0 {
-			mat[i][j].score
-		} else if mat[i][j].direction != Direction::Undefined {
-			mat[i][j].score
-		} else {
-			let up_left = get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i - 1, j - 1);
-			let up      = get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i - 1, j);
-			let left    = get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i, j - 1);
+	for i in 1..(vec1.len() + 1) {
+		for j in 1..(vec2.len() + 1) {
+			let up_left = mat[i-1][j-1].score;
+			let up = mat[i-1][j].score;
+			let left = mat[i][j-1].score;
 			// the dimension of the alignment matrix is one larger than vec1.len by vec2.len
 			let similarity_score = similarity(&vec1[i-1], &vec2[j-1]); 
-			let match_score =  up_left + similarity_score; 
+			let match_score = up_left + similarity_score; 
 			let up_score = up + gap_penalty; 
 			let left_score = left + gap_penalty;
-			let m = *[match_score, up_score, left_score].iter().max().unwrap();
+			mat[i][j] = best_entry(match_score, up_score, left_score, tie_break);
 			#[cfg(feature = "verbose")]
 			{
-				print_mat(mat);
 				println!("Node: {:?}", (i, j));
 				println!("up_left, up, left: {:?}", (up_left, up, left));
 				println!("Match, up, left: {:?}", (match_score, up_score, left_score));
-				println!("Max score: {}", m);
-			}
-			if m == match_score {
-				#[cfg(feature = "verbose")]
-				{
-					println!("Go Up and Left!");	
-				}
-				mat[i][j] = ResultEntry::new(m, Direction::Match);
-			} else if m == up_score {
-				#[cfg(feature = "verbose")]
-				{
-					println!("Go Up!");	
-				}
-				mat[i][j] = ResultEntry::new(m, Direction::Up);
-			} else {
-				#[cfg(feature = "verbose")]
-				{
-					println!("Go Left!");	
-				}
-				mat[i][j] = ResultEntry::new(m, Direction::Left);
+				println!("Max score: {}", mat[i][j].score);
 			}
-			m
-		}
-	}
-	for i in 1..(vec1.len() + 1) {
-		for j in 1..(vec2.len() + 1) {
-			get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i, j);
 		}
 	}
 	#[cfg(feature = "verbose")] 
 	{
-		print_mat(mat);
+		println!("{}", ::matrix_view::MatrixView::from_matrix(mat.clone(), Vec::new(), Vec::new(), None));
 	}
 }
 
-fn print_mat(mat: &AlignMatrix) {
-	use std::fmt::Write; 
-	fn direction_to_arrow(d: Direction) -> char {
-		match d {
-			Direction::Match => '↖', 
-			Direction::Up => '↑', 
-			Direction::Left => '←', 
-			Direction::Undefined => '.', 
+/// The entry of a cell from the scores of its three moves: the best score, every move
+/// reaching it and the one the tie-breaking policy picks.
+pub(crate) fn best_entry(match_score: i64, up_score: i64, left_score: i64, tie_break: TieBreak) -> ResultEntry {
+	let m = match_score.max(up_score).max(left_score);
+	let mut directions = Directions::default();
+	for &(score, d) in &[(match_score, Direction::Match), (up_score, Direction::Up), (left_score, Direction::Left)] {
+		if score == m {
+			directions.insert(d);
 		}
 	}
-	for row in mat {
-		let mut s = String::new(); 
-		for el in row { 
-			write!(&mut s, "{:>4}", el.score).unwrap(); 
-		} 
-		println!("{}", s);
+	ResultEntry {
+		score: m,
+		direction: tie_break.choose(directions),
+		directions,
 	}
-	for row in mat {
-		let mut s = String::new(); 
-		for el in row { 
-			write!(&mut s, "{:>4}", direction_to_arrow(el.direction)).unwrap(); 
-		} 
-		println!("{}", s);
+}
+
+/// Last row of the score matrix, computed with two rolling rows instead of the whole matrix:
+/// entry j is the optimal score of aligning all of vec1 with vec2[..j].
+pub fn last_row_scores<T>(vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> Vec<i64> {
+	let mut prev: Vec<i64> = (0..(vec2.len() as i64 + 1)).map(|j| j * gap_penalty).collect();
+	let mut cur = vec![0; vec2.len() + 1];
+	for (i, a) in vec1.iter().enumerate() {
+		cur[0] = (i as i64 + 1) * gap_penalty;
+		for (j, b) in vec2.iter().enumerate() {
+			let match_score = prev[j] + similarity(a, b);
+			let up_score = prev[j + 1] + gap_penalty;
+			let left_score = cur[j] + gap_penalty;
+			cur[j + 1] = match_score.max(up_score).max(left_score);
+		}
+		::std::mem::swap(&mut prev, &mut cur);
 	}
+	prev
+}
+
+/// Score of the optimal global alignment in O(len2) memory, without the alignment itself.
+pub fn align_score<T>(vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> i64 {
+	*last_row_scores(vec1, vec2, similarity, gap_penalty).last().unwrap()
 }
 
+#[test]
+fn test_align_score() {
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 1 } else { -1 }
+	}
+	let vec1: Vec<_> = "what".chars().collect();
+	let vec2: Vec<_> = "white".chars().collect();
+	assert_eq!(align_score(&vec1, &vec2, &similarity, -1), 1);
+	assert_eq!(last_row_scores(&vec1, &vec2, &similarity, -1), vec![-4, -2, 0, 0, 2, 1]);
+	assert_eq!(align_score(&vec1, &[], &similarity, -2), -8);
+	assert_eq!(align_score(&[], &vec2, &similarity, -1), -5);
+}
+
+/// The previous, recursive fill: every cell recursed into its three neighbours and relied on
+/// Direction::Undefined to tell whether a cell was computed yet. Kept to check and benchmark
+/// the iterative fill against.
+#[cfg(test)]
+fn fill_align_matrix_recursive<T>(mat: &mut AlignMatrix, vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) {
+	fn get_score_set_direction<U>(mat: &mut AlignMatrix, vec1: &[U], vec2: &[U], similarity: &dyn Fn(&U, &U) -> i64, gap_penalty: i64, i: usize, j: usize) -> i64 {
+		if i == 0 || j == 0 || mat[i][j].direction != Direction::Undefined {
+			return mat[i][j].score;
+		}
+		let up_left = get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i - 1, j - 1);
+		let up      = get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i - 1, j);
+		let left    = get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i, j - 1);
+		let match_score =  up_left + similarity(&vec1[i-1], &vec2[j-1]); 
+		let up_score = up + gap_penalty; 
+		let left_score = left + gap_penalty;
+		let m = *[match_score, up_score, left_score].iter().max().unwrap();
+		mat[i][j] = if m == match_score {
+			ResultEntry::new(m, Direction::Match)
+		} else if m == up_score {
+			ResultEntry::new(m, Direction::Up)
+		} else {
+			ResultEntry::new(m, Direction::Left)
+		};
+		m
+	}
+	for i in 1..(vec1.len() + 1) {
+		for j in 1..(vec2.len() + 1) {
+			get_score_set_direction(mat, vec1, vec2, similarity, gap_penalty, i, j);
+		}
+	}
+}
+
+#[cfg(test)]
+use test_support::random_dna;
+
+#[cfg(test)]
+fn dna_similarity(a: &u8, b: &u8) -> i64 {
+	if a == b { 2 } else { -1 }
+}
+
+#[test]
+fn test_fill_mat_iterative_matches_recursive() {
+	let vec1 = random_dna(120, 1);
+	let vec2 = random_dna(95, 2);
+	let mut iterative = init_align_matrix(vec1.len(), vec2.len(), -2, AlignMode::Global);
+	fill_align_matrix(&mut iterative, &vec1, &vec2, &dna_similarity, -2, TieBreak::default());
+	let mut recursive = init_align_matrix(vec1.len(), vec2.len(), -2, AlignMode::Global);
+	fill_align_matrix_recursive(&mut recursive, &vec1, &vec2, &dna_similarity, -2);
+	// the recursive fill only kept a single direction per cell
+	let chosen = |mat: &AlignMatrix| -> Vec<Vec<(i64, Direction)>> {
+		mat.iter().map(|row| row.iter().map(|e| (e.score, e.direction)).collect()).collect()
+	};
+	assert_eq!(chosen(&iterative), chosen(&recursive));
+	assert_eq!(iterative[120][95].score, align_score(&vec1, &vec2, &dna_similarity, -2));
+}
+
+#[cfg(test)]
+mod bench {
+	use test::Bencher;
+	use super::{AlignMode, init_align_matrix, fill_align_matrix, fill_align_matrix_recursive, align_score, random_dna, dna_similarity, TieBreak};
+
+	const LEN: usize = 1000;
+
+	#[bench]
+	fn bench_fill_recursive(b: &mut Bencher) {
+		let (vec1, vec2) = (random_dna(LEN, 1), random_dna(LEN, 2));
+		b.iter(|| {
+			let mut mat = init_align_matrix(LEN, LEN, -2, AlignMode::Global);
+			fill_align_matrix_recursive(&mut mat, &vec1, &vec2, &dna_similarity, -2);
+			mat[LEN][LEN].score
+		});
+	}
+
+	#[bench]
+	fn bench_fill_iterative(b: &mut Bencher) {
+		let (vec1, vec2) = (random_dna(LEN, 1), random_dna(LEN, 2));
+		b.iter(|| {
+			let mut mat = init_align_matrix(LEN, LEN, -2, AlignMode::Global);
+			fill_align_matrix(&mut mat, &vec1, &vec2, &dna_similarity, -2, TieBreak::default());
+			mat[LEN][LEN].score
+		});
+	}
+
+	#[bench]
+	fn bench_score_only(b: &mut Bencher) {
+		let (vec1, vec2) = (random_dna(LEN, 1), random_dna(LEN, 2));
+		b.iter(|| align_score(&vec1, &vec2, &dna_similarity, -2));
+	}
+}
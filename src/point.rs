@@ -1,13 +1,14 @@
-use std::ops::{Add, Sub, Mul};
+use std::ops::{Add, Sub, Mul, Neg};
 use std::cmp::Ordering;
 
 use super::definite_num::DefinitelyANumber;
+use super::coordinate::Coordinate;
 
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Point {
-    pub x: DefinitelyANumber,
-    pub y: DefinitelyANumber,
+pub struct Point<T = DefinitelyANumber> {
+    pub x: T,
+    pub y: T,
 }
 
 impl Point {
@@ -18,22 +19,70 @@ impl Point {
         }
     }
 
+	pub fn rotate(&self, theta: f64) -> Point {
+		let x = self.x.to_f64(); 
+		let y = self.y.to_f64(); 
+		let cosine = theta.cos();
+		let sine = theta.sin(); 
+		let x_cos_theta = x * cosine; 
+		let x_sin_theta = x * sine; 
+		let y_cos_theta = y * cosine; 
+		let y_sin_theta = y * sine; 
+		let x1 = x_cos_theta - y_sin_theta; 
+		let y1 = x_sin_theta + y_cos_theta;
+		Point::new(x1, y1)
+	}
+
+	// unit vector with the same direction, None for the zero vector
+	pub fn normalize(&self) -> Option<Point> {
+		let mag = self.magnitude();
+		if mag == 0.0 {
+			None
+		} else {
+			Some(*self * mag.recip())
+		}
+	}
+
+	// projection of this vector onto the line spanned by other, None if other is the zero vector
+	pub fn project_onto(&self, other: &Point) -> Option<Point> {
+		let denom = other.dot(other);
+		if denom == 0.0 {
+			None
+		} else {
+			Some(*other * (self.dot(other) / denom))
+		}
+	}
+}
+
+impl<T> Point<T> where T: Coordinate {
+    pub fn from_coords(x: T, y: T) -> Point<T> {
+        Point { x, y }
+    }
+
+    // differences of the coordinates from this point to the other one, taken after widening
+    // so that they cannot overflow T
+    fn delta(&self, other: &Point<T>) -> (f64, f64) {
+        (T::wide_to_f64(other.x.to_wide() - self.x.to_wide()), T::wide_to_f64(other.y.to_wide() - self.y.to_wide()))
+    }
+
     // Euclidean distance
-    pub fn distance(&self, other: &Point) -> f64 {
-        ((self.x - other.x).to_f64().powi(2) + (self.y - other.y).to_f64().powi(2)).sqrt()
+    pub fn distance(&self, other: &Point<T>) -> f64 {
+        let (dx, dy) = self.delta(other);
+        (dx * dx + dy * dy).sqrt()
     }
 
     // Draw a horizontal line through this point, connect this point with the other, and measure the angle between these two lines.
-    pub fn angle(&self, other: &Point) -> f64 {
+    pub fn angle(&self, other: &Point<T>) -> f64 {
         if self == other {
             0.0
         } else {
-            (other.y - self.y).to_f64().atan2((other.x - self.x).to_f64())
+            let (dx, dy) = self.delta(other);
+            dy.atan2(dx)
         }
     }
 	
 	pub fn magnitude(&self) -> f64 {
-        (self.x.to_f64().powi(2) + self.y.to_f64().powi(2)).sqrt()
+        T::wide_to_f64(self.dot(self)).sqrt()
 	}
 
 	pub fn sin_cos(&self) -> (f64, f64) {
@@ -41,31 +90,38 @@ impl Point {
 		(self.y.to_f64() / mag, self.x.to_f64() / mag)
 	}
 
-	pub fn rotate(&self, theta: f64) -> Point {
-		let x = self.x.to_f64(); 
-		let y = self.y.to_f64(); 
-		let cosine = theta.cos();
-		let sine = theta.sin(); 
-		let x_cos_theta = x * cosine; 
-		let x_sin_theta = x * sine; 
-		let y_cos_theta = y * cosine; 
-		let y_sin_theta = y * sine; 
-		let x1 = x_cos_theta - y_sin_theta; 
-		let y1 = x_sin_theta + y_cos_theta;
-		Point::new(x1, y1)
+	// dot product, same as `*self * *other`
+	pub fn dot(&self, other: &Point<T>) -> T::Wide {
+		*self * *other
 	}
-	
-	pub fn direction(&self, p1: &Point, p2: &Point) -> Direction {
-		let v1 = *p1 - *self; 
-		let v2 = *p2 - *self;
-		let x1 = v1.x.to_f64(); 
-		let x2 = v2.x.to_f64(); 
-		let y1 = v1.y.to_f64(); 
-		let y2 = v2.y.to_f64(); 
-		let det = x1 * y2 - y1 * x2; 
-		if det < 0.0 {
+
+	// z component of the 3d cross product, positive when other is counter-clockwise from self
+	pub fn cross(&self, other: &Point<T>) -> T::Wide {
+		self.x.to_wide() * other.y.to_wide() - self.y.to_wide() * other.x.to_wide()
+	}
+
+	// the vector rotated by 90 degrees counter-clockwise
+	pub fn perpendicular(&self) -> Point<T> {
+		Point {
+			x: -self.y,
+			y: self.x,
+		}
+	}
+
+	// unsigned angle between the two vectors, in [0, pi]
+	pub fn angle_between(&self, other: &Point<T>) -> f64 {
+		T::wide_to_f64(self.cross(other)).abs().atan2(T::wide_to_f64(self.dot(other)))
+	}
+
+	// exact for integer (within the range documented on Coordinate) and rational coordinates:
+	// the differences are taken after widening, so they cannot overflow T
+	pub fn direction(&self, p1: &Point<T>, p2: &Point<T>) -> Direction {
+		let (x, y) = (self.x.to_wide(), self.y.to_wide());
+		let det = (p1.x.to_wide() - x) * (p2.y.to_wide() - y) - (p1.y.to_wide() - y) * (p2.x.to_wide() - x);
+		let zero = T::wide_zero();
+		if det < zero {
 			Direction::Right
-		} else if det > 0.0 {
+		} else if det > zero {
 			Direction::Left
 		} else {
 			Direction::Ahead
@@ -80,18 +136,18 @@ pub enum Direction {
 	Ahead,
 }
 
-impl Add for Point {
-    type Output = Point;
-    fn add(self, rhs: Point) -> Point {
+impl<T> Add for Point<T> where T: Coordinate {
+    type Output = Point<T>;
+    fn add(self, rhs: Point<T>) -> Point<T> {
         Point {
             x: self.x + rhs.x,
             y: self.y + rhs.y,
         }
     }
 }
-impl Sub for Point {
-    type Output = Point;
-    fn sub(self, rhs: Point) -> Point {
+impl<T> Sub for Point<T> where T: Coordinate {
+    type Output = Point<T>;
+    fn sub(self, rhs: Point<T>) -> Point<T> {
         Point {
             x: self.x - rhs.x,
             y: self.y - rhs.y,
@@ -99,21 +155,39 @@ impl Sub for Point {
     }
 }
 // dot product
-impl Mul for Point {
-    type Output = f64;
-    fn mul(self, rhs: Point) -> f64 {
-        (self.x * rhs.x + self.y * rhs.y).to_f64()
+impl<T> Mul for Point<T> where T: Coordinate {
+    type Output = T::Wide;
+    fn mul(self, rhs: Point<T>) -> T::Wide {
+        self.x.to_wide() * rhs.x.to_wide() + self.y.to_wide() * rhs.y.to_wide()
+    }
+}
+// scalar multiplication
+impl Mul<f64> for Point {
+    type Output = Point;
+    fn mul(self, rhs: f64) -> Point {
+        Point::new(self.x.to_f64() * rhs, self.y.to_f64() * rhs)
+    }
+}
+impl<T> Neg for Point<T> where T: Coordinate {
+    type Output = Point<T>;
+    fn neg(self) -> Point<T> {
+        Point {
+            x: -self.x,
+            y: -self.y,
+        }
     }
 }
 
 // sort by angle to head
-pub fn sort_points(points: &mut Vec<Point>) {
+pub fn sort_points<T>(points: &mut [Point<T>]) where T: Coordinate {
 	// sort by coordinates so that the first point is the leftmost
     points.sort();
     let head = points[0];
     // sort by the angle with the first point
     // when that is equal, sort by x
     // when that is equal, sort by y
+    // All points lie to the right of head (or straight above it), so the angle comparison
+    // can be done exactly with the orientation test instead of atan2.
     points.sort_by(|a, b| {
 		// head always comes first. 
 		if a == &head {
@@ -122,13 +196,10 @@ pub fn sort_points(points: &mut Vec<Point>) {
 		if b == &head {
 			return Ordering::Greater
 		}
-        let angle_a = head.angle(&a);
-        let angle_b = head.angle(&b);
-        let angle_cmp = angle_a.partial_cmp(&angle_b).unwrap(); 	
-        if angle_cmp == Ordering::Equal {
-			a.cmp(&b)
-        } else {
-        	angle_cmp
+        match head.direction(a, b) {
+			Direction::Left => Ordering::Less,
+			Direction::Right => Ordering::Greater,
+			Direction::Ahead => a.cmp(b),
         }
     });
 }
@@ -136,8 +207,10 @@ pub fn sort_points(points: &mut Vec<Point>) {
 
 #[cfg(test)]
 mod test {
-	use point::Point;
+	use point::{Point, sort_points};
 	use super::Direction;
+	use rational::Rational;
+	use rect::Rect;
 	#[test]
     fn test_point() {
         use std::f64::consts::PI;
@@ -191,4 +264,82 @@ mod test {
 		assert_eq!(p1.direction(&p2, &p3), Direction::Right);
     }
 
-}
\ No newline at end of file
+	#[test]
+    fn test_vector_algebra() {
+        use std::f64::consts::PI;
+        let v1 = Point::new(3.0, 4.0);
+        let v2 = Point::new(1.0, 0.0);
+        assert_eq!(v1 * 2.0, Point::new(6.0, 8.0));
+        assert_eq!(-v1, Point::new(-3.0, -4.0));
+        assert_eq!(v1.dot(&v2), 3.0);
+        assert_eq!(v2.cross(&v1), 4.0);
+        assert_eq!(v1.cross(&v2), -4.0);
+        assert_eq!(Point::new(0.0, 5.0).normalize(), Some(Point::new(0.0, 1.0)));
+        assert!((v1.normalize().unwrap().magnitude() - 1.0).abs() < 1e-12);
+        assert_eq!(Point::new(0.0, 0.0).normalize(), None);
+        assert_eq!(v1.perpendicular(), Point::new(-4.0, 3.0));
+        assert_eq!(v1.perpendicular().dot(&v1), 0.0);
+        assert_eq!(v1.project_onto(&v2), Some(Point::new(3.0, 0.0)));
+        assert_eq!(v1.project_onto(&Point::new(0.0, 0.0)), None);
+        assert_eq!(v2.angle_between(&Point::new(0.0, 2.0)), PI / 2.0);
+        assert_eq!(v2.angle_between(&Point::new(0.0, -2.0)), PI / 2.0);
+        assert_eq!(v2.angle_between(&Point::new(-1.0, 0.0)), PI);
+        assert_eq!(v2.angle_between(&v2), 0.0);
+    }
+
+	#[test]
+    fn test_integer_points() {
+        use std::f64::consts::PI;
+        // coordinates large enough that the determinant overflows i64 and is inexact in f64
+        let big = 3_000_000_000_000_000_000i64;
+        let p1 = Point::from_coords(0i64, 0);
+        let p2 = Point::from_coords(big, big - 1);
+        let p3 = Point::from_coords(big - 1, big - 2);
+        assert_eq!(p1.direction(&p2, &p3), Direction::Right);
+        assert_eq!(p1.direction(&p3, &p2), Direction::Left);
+        assert_eq!(p1.direction(&p2, &(p2 + p2)), Direction::Ahead);
+        // the differences to a point at the other extreme overflow the coordinate type itself
+        let (lo, hi) = (i32::MIN, i32::MAX);
+        let origin = Point::from_coords(lo, lo);
+        assert_eq!(origin.direction(&Point::from_coords(hi, hi - 1), &Point::from_coords(hi - 1, hi)), Direction::Left);
+        assert_eq!(origin.direction(&Point::from_coords(hi - 1, hi), &Point::from_coords(hi, hi - 1)), Direction::Right);
+        assert_eq!(origin.direction(&Point::from_coords(hi, hi), &Point::from_coords(0, 0)), Direction::Ahead);
+        let limit = 1i64 << 62;
+        let origin = Point::from_coords(-limit, -limit);
+        assert_eq!(origin.direction(&Point::from_coords(limit, limit - 1), &Point::from_coords(limit - 1, limit)), Direction::Left);
+        assert_eq!(origin.direction(&Point::from_coords(limit, limit), &Point::from_coords(0, 0)), Direction::Ahead);
+        // distances and angles between far apart points, the differences overflow i64
+        let half = i64::MAX / 2;
+        let (p1, p2) = (Point::from_coords(-half, -half), Point::from_coords(half, half));
+        let diagonal = (i64::MAX - 1) as f64 * 2f64.sqrt();
+        assert!((p1.distance(&p2) - diagonal).abs() <= diagonal * 1e-15);
+        assert_eq!(p1.angle(&p2), PI / 4.0);
+        assert_eq!(p2.angle(&p1), -3.0 * PI / 4.0);
+        let (p1, p2) = (Point::from_coords(i32::MIN, 0), Point::from_coords(i32::MAX, 0));
+        assert_eq!(p1.distance(&p2), u32::MAX as f64);
+        let r = Rect::new(Point::from_coords(-half, -half), Point::from_coords(0, 0));
+        assert_eq!(r.distance(&Point::from_coords(half, 0)), half as f64);
+        let v = Point::from_coords(3i32, 4);
+        assert_eq!(v.dot(&v), 25i128);
+        assert_eq!(v.cross(&Point::from_coords(1, 0)), -4i128);
+        assert_eq!(v.perpendicular(), Point::from_coords(-4, 3));
+        assert_eq!(v.magnitude(), 5.0);
+        let mut points = vec![Point::from_coords(2i32, 2), Point::from_coords(1, 0), Point::from_coords(0, 0),
+                              Point::from_coords(1, 1), Point::from_coords(0, 1)];
+        sort_points(&mut points);
+        assert_eq!(points, vec![Point::from_coords(0, 0), Point::from_coords(1, 0), Point::from_coords(1, 1),
+                                Point::from_coords(2, 2), Point::from_coords(0, 1)]);
+    }
+
+	#[test]
+    fn test_rational_points() {
+        let third = Rational::new(1, 3);
+        let p1 = Point::from_coords(Rational::from_integer(0), Rational::from_integer(0));
+        let p2 = Point::from_coords(third, third);
+        let p3 = Point::from_coords(third + third, third + third);
+        assert_eq!(p1.direction(&p2, &p3), Direction::Ahead);
+        assert_eq!(p2.cross(&p3), Rational::from_integer(0));
+        assert_eq!(p2.dot(&p3), Rational::new(4, 9));
+    }
+
+}
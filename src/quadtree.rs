@@ -0,0 +1,296 @@
+use point::Point;
+use rect::Rect;
+use coordinate::Coordinate;
+use definite_num::DefinitelyANumber;
+
+/// A dynamic point-region quadtree. A node holds up to `capacity` points before it is split
+/// into four quadrants around the center of its bounds; nodes at `max_depth` are never split,
+/// so arbitrarily many (e.g. duplicate) points can end up there.
+pub struct Quadtree<T = DefinitelyANumber> {
+    root: Node<T>,
+    capacity: usize,
+    max_depth: usize,
+    len: usize,
+}
+
+struct Node<T> {
+    bounds: Rect<T>,
+    depth: usize,
+    points: Vec<Point<T>>,
+    // south-west, south-east, north-west, north-east
+    children: Option<Box<[Node<T>; 4]>>,
+}
+
+fn center<T>(bounds: &Rect<T>) -> Point<T> where T: Coordinate {
+    Point::from_coords(bounds.min.x.midway(bounds.max.x), bounds.min.y.midway(bounds.max.y))
+}
+
+// points on the center lines belong to the south/west quadrants
+fn quadrant<T>(bounds: &Rect<T>, p: &Point<T>) -> usize where T: Coordinate {
+    let c = center(bounds);
+    (if p.x <= c.x { 0 } else { 1 }) + (if p.y <= c.y { 0 } else { 2 })
+}
+
+impl<T> Node<T> where T: Coordinate {
+    fn new(bounds: Rect<T>, depth: usize) -> Node<T> {
+        Node {
+            bounds,
+            depth,
+            points: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn split(&mut self) {
+        let (min, max, c) = (self.bounds.min, self.bounds.max, center(&self.bounds));
+        let depth = self.depth + 1;
+        self.children = Some(Box::new([
+            Node::new(Rect::new(min, c), depth),
+            Node::new(Rect::new(Point::from_coords(c.x, min.y), Point::from_coords(max.x, c.y)), depth),
+            Node::new(Rect::new(Point::from_coords(min.x, c.y), Point::from_coords(c.x, max.y)), depth),
+            Node::new(Rect::new(c, max), depth),
+        ]));
+        let children = self.children.as_mut().unwrap();
+        for p in std::mem::take(&mut self.points) {
+            children[quadrant(&self.bounds, &p)].points.push(p);
+        }
+    }
+
+    fn insert(&mut self, p: Point<T>, capacity: usize, max_depth: usize) {
+        if let Some(ref mut children) = self.children {
+            children[quadrant(&self.bounds, &p)].insert(p, capacity, max_depth);
+            return;
+        }
+        self.points.push(p);
+        if self.points.len() > capacity && self.depth < max_depth {
+            self.split();
+            // all points may have landed in the same quadrant
+            for child in self.children.as_mut().unwrap().iter_mut() {
+                if child.points.len() > capacity && child.depth < max_depth {
+                    for p in std::mem::take(&mut child.points) {
+                        child.insert(p, capacity, max_depth);
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, p: &Point<T>, capacity: usize) -> bool {
+        let removed = match self.children {
+            None => match self.points.iter().position(|x| x == p) {
+                Some(i) => {
+                    self.points.swap_remove(i);
+                    true
+                },
+                None => false,
+            },
+            Some(ref mut children) => children[quadrant(&self.bounds, p)].remove(p, capacity),
+        };
+        // merge the quadrants back once they fit into this node again
+        if removed && self.children.is_some() && self.count() <= capacity {
+            let mut points = Vec::new();
+            self.collect(&mut points);
+            self.points = points;
+            self.children = None;
+        }
+        removed
+    }
+
+    fn count(&self) -> usize {
+        match self.children {
+            None => self.points.len(),
+            Some(ref children) => children.iter().map(|c| c.count()).sum(),
+        }
+    }
+
+    fn collect(&self, res: &mut Vec<Point<T>>) {
+        match self.children {
+            None => res.extend(self.points.iter().cloned()),
+            Some(ref children) => {
+                for c in children.iter() {
+                    c.collect(res);
+                }
+            },
+        }
+    }
+
+    fn query<'a, F, G>(&'a self, overlaps: &F, accept: &G, res: &mut Vec<&'a Point<T>>)
+        where F: Fn(&Rect<T>) -> bool, G: Fn(&Point<T>) -> bool {
+        if !overlaps(&self.bounds) {
+            return;
+        }
+        match self.children {
+            None => res.extend(self.points.iter().filter(|p| accept(p))),
+            Some(ref children) => {
+                for c in children.iter() {
+                    c.query(overlaps, accept, res);
+                }
+            },
+        }
+    }
+}
+
+impl<T> Quadtree<T> where T: Coordinate {
+    pub fn new(bounds: Rect<T>, capacity: usize, max_depth: usize) -> Quadtree<T> {
+        assert!(capacity > 0, "Bucket capacity must be positive!");
+        Quadtree {
+            root: Node::new(bounds, 0),
+            capacity,
+            max_depth,
+            len: 0,
+        }
+    }
+
+    pub fn bounds(&self) -> Rect<T> {
+        self.root.bounds
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns false (and does not store the point) if it lies outside the bounds of the tree.
+    pub fn insert(&mut self, p: Point<T>) -> bool {
+        if !self.root.bounds.contains(&p) {
+            return false;
+        }
+        self.root.insert(p, self.capacity, self.max_depth);
+        self.len += 1;
+        true
+    }
+
+    /// Removes one copy of the point, returns false if it was not stored.
+    pub fn remove(&mut self, p: &Point<T>) -> bool {
+        let removed = self.root.remove(p, self.capacity);
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    pub fn points(&self) -> Vec<Point<T>> {
+        let mut res = Vec::new();
+        self.root.collect(&mut res);
+        res
+    }
+
+    /// All stored points inside the rectangle, boundary included.
+    pub fn query_rect(&self, rect: &Rect<T>) -> Vec<&Point<T>> {
+        let mut res = Vec::new();
+        self.root.query(&|b: &Rect<T>| b.intersects(rect), &|p: &Point<T>| rect.contains(p), &mut res);
+        res
+    }
+
+    /// All stored points within the given distance of center, boundary included.
+    pub fn query_circle(&self, center: &Point<T>, radius: f64) -> Vec<&Point<T>> {
+        let mut res = Vec::new();
+        self.root.query(&|b: &Rect<T>| b.distance(center) <= radius,
+                        &|p: &Point<T>| p.distance(center) <= radius, &mut res);
+        res
+    }
+
+    /// Pairs of stored points that are at most `distance` apart, i.e. the colliding pairs when
+    /// every item is a disc of diameter `distance`. Every pair is reported once.
+    pub fn collisions(&self, distance: f64) -> Vec<(Point<T>, Point<T>)> {
+        let mut stored = Vec::new();
+        self.root.query(&|_: &Rect<T>| true, &|_: &Point<T>| true, &mut stored);
+        let mut res = Vec::new();
+        for p in stored {
+            // compare storage addresses so that equal points stored twice still form one pair
+            for q in self.query_circle(p, distance) {
+                if (p as *const Point<T>) < (q as *const Point<T>) {
+                    res.push((*p, *q));
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use point::Point;
+    use rect::Rect;
+    use quadtree::Quadtree;
+    use test_support::Lcg;
+
+    fn random_points(n: usize) -> Vec<Point<i32>> {
+        let mut rng = Lcg::new(7);
+        (0..n).map(|_| Point::from_coords(rng.below(512) as i32, rng.below(512) as i32)).collect()
+    }
+
+    fn sorted(mut v: Vec<Point<i32>>) -> Vec<Point<i32>> {
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn test_quadtree_insert_remove() {
+        let bounds = Rect::new(Point::from_coords(0, 0), Point::from_coords(511, 511));
+        let mut tree = Quadtree::new(bounds, 4, 8);
+        let points = random_points(1000);
+        for p in &points {
+            assert!(tree.insert(*p));
+        }
+        assert!(!tree.insert(Point::from_coords(512, 0)));
+        assert_eq!(tree.len(), 1000);
+        assert_eq!(sorted(tree.points()), sorted(points.clone()));
+        for p in &points[..900] {
+            assert!(tree.remove(p));
+        }
+        assert!(!tree.remove(&Point::from_coords(-1, 0)));
+        assert_eq!(tree.len(), 100);
+        assert_eq!(sorted(tree.points()), sorted(points[900..].to_vec()));
+        for p in &points[900..] {
+            assert!(tree.remove(p));
+        }
+        assert!(tree.is_empty());
+        assert!(tree.root.children.is_none());
+    }
+
+    #[test]
+    fn test_quadtree_duplicates() {
+        let bounds = Rect::new(Point::from_coords(0, 0), Point::from_coords(10, 10));
+        let mut tree = Quadtree::new(bounds, 2, 3);
+        for _ in 0..10 {
+            tree.insert(Point::from_coords(3, 3));
+        }
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.query_circle(&Point::from_coords(3, 3), 0.0).len(), 10);
+        assert_eq!(tree.collisions(0.5).len(), 45);
+    }
+
+    #[test]
+    fn test_quadtree_queries() {
+        let bounds = Rect::new(Point::from_coords(0, 0), Point::from_coords(511, 511));
+        let mut tree = Quadtree::new(bounds, 8, 10);
+        let points = random_points(2000);
+        for p in &points {
+            tree.insert(*p);
+        }
+        let rect = Rect::new(Point::from_coords(30, 100), Point::from_coords(200, 180));
+        let found = sorted(tree.query_rect(&rect).into_iter().cloned().collect());
+        assert_eq!(found, sorted(points.iter().filter(|p| rect.contains(p)).cloned().collect()));
+        let center = Point::from_coords(256, 256);
+        let found = sorted(tree.query_circle(&center, 40.0).into_iter().cloned().collect());
+        assert_eq!(found, sorted(points.iter().filter(|p| p.distance(&center) <= 40.0).cloned().collect()));
+
+        let mut collisions: Vec<_> = tree.collisions(5.0).into_iter().map(|(p, q)| (p.min(q), p.max(q))).collect();
+        let mut should_be = Vec::new();
+        for i in 0..points.len() {
+            for j in (i + 1)..points.len() {
+                if points[i].distance(&points[j]) <= 5.0 {
+                    should_be.push((points[i].min(points[j]), points[i].max(points[j])));
+                }
+            }
+        }
+        collisions.sort();
+        should_be.sort();
+        assert!(!should_be.is_empty());
+        assert_eq!(collisions, should_be);
+    }
+}
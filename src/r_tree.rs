@@ -0,0 +1,343 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::mem;
+
+use point::Point;
+use rect::Rect;
+use coordinate::Coordinate;
+use definite_num::DefinitelyANumber;
+
+/// An R-tree over axis-aligned rectangles, each carrying a value (e.g. the id of the polygon
+/// it bounds). Overflowing nodes are divided with Guttman's quadratic split, and `bulk_load`
+/// packs a whole data set at once with Sort-Tile-Recursive.
+pub struct RTree<V, T = DefinitelyANumber> {
+    root: Node<V, T>,
+    max_entries: usize,
+    min_entries: usize,
+    len: usize,
+}
+
+type Entries<T, X> = Vec<(Rect<T>, X)>;
+
+enum Node<V, T> {
+    Leaf(Vec<(Rect<T>, V)>),
+    Inner(Vec<(Rect<T>, Node<V, T>)>),
+}
+
+fn mbr<T, X>(entries: &[(Rect<T>, X)]) -> Rect<T> where T: Coordinate {
+    let mut res = entries[0].0;
+    for e in &entries[1..] {
+        res = res.union(&e.0);
+    }
+    res
+}
+
+fn node_mbr<V, T>(node: &Node<V, T>) -> Rect<T> where T: Coordinate {
+    match *node {
+        Node::Leaf(ref entries) => mbr(entries),
+        Node::Inner(ref entries) => mbr(entries),
+    }
+}
+
+fn enlargement<T>(r: &Rect<T>, add: &Rect<T>) -> f64 where T: Coordinate {
+    r.union(add).area() - r.area()
+}
+
+// Guttman's quadratic split: start from the pair that would waste the most area when grouped
+// together, then repeatedly assign the entry with the strongest preference for one group.
+fn quadratic_split<T, X>(mut entries: Entries<T, X>, min_entries: usize) -> (Entries<T, X>, Entries<T, X>)
+    where T: Coordinate {
+    let (mut s1, mut s2, mut worst) = (0, 1, f64::NEG_INFINITY);
+    for i in 0..entries.len() {
+        for j in (i + 1)..entries.len() {
+            let (a, b) = (&entries[i].0, &entries[j].0);
+            let waste = a.union(b).area() - a.area() - b.area();
+            if waste > worst {
+                s1 = i;
+                s2 = j;
+                worst = waste;
+            }
+        }
+    }
+    // s1 < s2, so removing s2 first keeps s1 valid
+    let seed2 = entries.swap_remove(s2);
+    let seed1 = entries.swap_remove(s1);
+    let (mut r1, mut r2) = (seed1.0, seed2.0);
+    let (mut g1, mut g2) = (vec![seed1], vec![seed2]);
+    while !entries.is_empty() {
+        if g1.len() + entries.len() == min_entries {
+            g1.append(&mut entries);
+            break;
+        }
+        if g2.len() + entries.len() == min_entries {
+            g2.append(&mut entries);
+            break;
+        }
+        let mut next = 0;
+        let mut best_diff = f64::NEG_INFINITY;
+        for (i, e) in entries.iter().enumerate() {
+            let diff = (enlargement(&r1, &e.0) - enlargement(&r2, &e.0)).abs();
+            if diff > best_diff {
+                next = i;
+                best_diff = diff;
+            }
+        }
+        let e = entries.swap_remove(next);
+        let (d1, d2) = (enlargement(&r1, &e.0), enlargement(&r2, &e.0));
+        let to_first = match d1.partial_cmp(&d2).unwrap() {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            Ordering::Equal => match r1.area().partial_cmp(&r2.area()).unwrap() {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => g1.len() <= g2.len(),
+            },
+        };
+        if to_first {
+            r1 = r1.union(&e.0);
+            g1.push(e);
+        } else {
+            r2 = r2.union(&e.0);
+            g2.push(e);
+        }
+    }
+    (g1, g2)
+}
+
+fn insert<V, T>(node: &mut Node<V, T>, rect: Rect<T>, value: V, max_entries: usize, min_entries: usize)
+    -> Option<(Rect<T>, Node<V, T>)> where T: Coordinate {
+    match *node {
+        Node::Leaf(ref mut entries) => {
+            entries.push((rect, value));
+            if entries.len() > max_entries {
+                let (a, b) = quadratic_split(mem::take(entries), min_entries);
+                *entries = a;
+                return Some((mbr(&b), Node::Leaf(b)));
+            }
+            None
+        },
+        Node::Inner(ref mut children) => {
+            // least enlargement, ties resolved by the smaller area
+            let mut best = 0;
+            for i in 1..children.len() {
+                let (ei, eb) = (enlargement(&children[i].0, &rect), enlargement(&children[best].0, &rect));
+                if ei < eb || (ei == eb && children[i].0.area() < children[best].0.area()) {
+                    best = i;
+                }
+            }
+            children[best].0 = children[best].0.union(&rect);
+            if let Some(sibling) = insert(&mut children[best].1, rect, value, max_entries, min_entries) {
+                children[best].0 = node_mbr(&children[best].1);
+                children.push(sibling);
+                if children.len() > max_entries {
+                    let (a, b) = quadratic_split(mem::take(children), min_entries);
+                    *children = a;
+                    return Some((mbr(&b), Node::Inner(b)));
+                }
+            }
+            None
+        },
+    }
+}
+
+// Sort-Tile-Recursive: sort by x, cut into vertical slabs, sort every slab by y and cut it into nodes.
+fn str_pack<T, X>(mut entries: Entries<T, X>, max_entries: usize) -> Vec<Entries<T, X>> where T: Coordinate {
+    let nodes = entries.len().div_ceil(max_entries);
+    let slabs = (nodes as f64).sqrt().ceil() as usize;
+    let slab_size = slabs * max_entries;
+    entries.sort_by_key(|e| e.0.center().x);
+    let mut res = Vec::with_capacity(nodes);
+    while !entries.is_empty() {
+        let rest = entries.split_off(slab_size.min(entries.len()));
+        let mut slab = mem::replace(&mut entries, rest);
+        slab.sort_by_key(|e| e.0.center().y);
+        while !slab.is_empty() {
+            let rest = slab.split_off(max_entries.min(slab.len()));
+            res.push(mem::replace(&mut slab, rest));
+        }
+    }
+    res
+}
+
+fn search<'a, V, T, F, G>(node: &'a Node<V, T>, descend: &F, accept: &G, res: &mut Vec<(&'a Rect<T>, &'a V)>)
+    where T: Coordinate, F: Fn(&Rect<T>) -> bool, G: Fn(&Rect<T>) -> bool {
+    match *node {
+        Node::Leaf(ref entries) => res.extend(entries.iter().filter(|e| accept(&e.0)).map(|e| (&e.0, &e.1))),
+        Node::Inner(ref children) => {
+            for c in children.iter().filter(|c| descend(&c.0)) {
+                search(&c.1, descend, accept, res);
+            }
+        },
+    }
+}
+
+enum Candidate<'a, V: 'a, T: 'a> {
+    Node(&'a Node<V, T>),
+    Entry(&'a Rect<T>, &'a V),
+}
+
+impl<V, T> RTree<V, T> where T: Coordinate {
+    pub fn new(max_entries: usize) -> RTree<V, T> {
+        assert!(max_entries >= 2, "A node must hold at least two entries!");
+        RTree {
+            root: Node::Leaf(Vec::new()),
+            max_entries,
+            min_entries: (max_entries * 2 / 5).max(1),
+            len: 0,
+        }
+    }
+
+    pub fn bulk_load(entries: Vec<(Rect<T>, V)>, max_entries: usize) -> RTree<V, T> {
+        let mut tree = RTree::new(max_entries);
+        tree.len = entries.len();
+        if entries.is_empty() {
+            return tree;
+        }
+        let mut level: Vec<(Rect<T>, Node<V, T>)> = str_pack(entries, max_entries)
+            .into_iter()
+            .map(|leaf| (mbr(&leaf), Node::Leaf(leaf)))
+            .collect();
+        while level.len() > 1 {
+            level = str_pack(level, max_entries)
+                .into_iter()
+                .map(|inner| (mbr(&inner), Node::Inner(inner)))
+                .collect();
+        }
+        tree.root = level.pop().unwrap().1;
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, rect: Rect<T>, value: V) {
+        if let Some(sibling) = insert(&mut self.root, rect, value, self.max_entries, self.min_entries) {
+            let old_root = mem::replace(&mut self.root, Node::Leaf(Vec::new()));
+            self.root = Node::Inner(vec![(node_mbr(&old_root), old_root), sibling]);
+        }
+        self.len += 1;
+    }
+
+    /// Entries whose rectangle shares at least one point with the query.
+    pub fn intersecting(&self, query: &Rect<T>) -> Vec<(&Rect<T>, &V)> {
+        let mut res = Vec::new();
+        search(&self.root, &|r: &Rect<T>| r.intersects(query), &|r: &Rect<T>| r.intersects(query), &mut res);
+        res
+    }
+
+    /// Entries whose rectangle lies completely inside the query.
+    pub fn within(&self, query: &Rect<T>) -> Vec<(&Rect<T>, &V)> {
+        let mut res = Vec::new();
+        search(&self.root, &|r: &Rect<T>| r.intersects(query), &|r: &Rect<T>| query.contains_rect(r), &mut res);
+        res
+    }
+
+    /// Entries whose rectangle completely covers the query.
+    pub fn containing(&self, query: &Rect<T>) -> Vec<(&Rect<T>, &V)> {
+        let mut res = Vec::new();
+        search(&self.root, &|r: &Rect<T>| r.contains_rect(query), &|r: &Rect<T>| r.contains_rect(query), &mut res);
+        res
+    }
+
+    /// The entry whose rectangle is closest to p (distance 0 when p is inside), found by
+    /// best-first search: node distances are lower bounds, so the first entry popped wins.
+    pub fn nearest(&self, p: &Point<T>) -> Option<(&Rect<T>, &V)> {
+        let mut candidates = vec![Candidate::Node(&self.root)];
+        let mut heap = BinaryHeap::new();
+        heap.push((Reverse(DefinitelyANumber::new(0.0).unwrap()), 0));
+        while let Some((_, i)) = heap.pop() {
+            match candidates[i] {
+                Candidate::Entry(r, v) => return Some((r, v)),
+                Candidate::Node(Node::Leaf(entries)) => {
+                    for e in entries {
+                        heap.push((Reverse(DefinitelyANumber::new(e.0.distance(p)).unwrap()), candidates.len()));
+                        candidates.push(Candidate::Entry(&e.0, &e.1));
+                    }
+                },
+                Candidate::Node(Node::Inner(children)) => {
+                    for c in children {
+                        heap.push((Reverse(DefinitelyANumber::new(c.0.distance(p)).unwrap()), candidates.len()));
+                        candidates.push(Candidate::Node(&c.1));
+                    }
+                },
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use point::Point;
+    use rect::Rect;
+    use triangle::Triangle;
+    use r_tree::RTree;
+    use test_support::Lcg;
+
+    // bounding boxes of pseudo random triangles in a 1000 x 1000 square
+    fn random_rects(n: usize) -> Vec<(Rect<i64>, usize)> {
+        let mut rng = Lcg::new(3);
+        let mut next = move |m: u64| rng.below(m) as i64;
+        (0..n).map(|i| {
+            let p = Point::from_coords(next(1000), next(1000));
+            let q = p + Point::from_coords(next(40), next(40));
+            let r = p + Point::from_coords(next(40), -next(40));
+            (Triangle::new(p, q, r).bounds(), i)
+        }).collect()
+    }
+
+    fn ids(found: Vec<(&Rect<i64>, &usize)>) -> Vec<usize> {
+        let mut res: Vec<_> = found.into_iter().map(|(_, i)| *i).collect();
+        res.sort();
+        res
+    }
+
+    fn check(tree: &RTree<usize, i64>, rects: &[(Rect<i64>, usize)]) {
+        assert_eq!(tree.len(), rects.len());
+        let query = Rect::new(Point::from_coords(200, 300), Point::from_coords(450, 380));
+        let brute = |f: &dyn Fn(&Rect<i64>) -> bool| -> Vec<usize> {
+            rects.iter().filter(|e| f(&e.0)).map(|e| e.1).collect()
+        };
+        let should_be = brute(&|r| r.intersects(&query));
+        assert!(!should_be.is_empty());
+        assert_eq!(ids(tree.intersecting(&query)), should_be);
+        assert_eq!(ids(tree.within(&query)), brute(&|r| query.contains_rect(r)));
+        let small = Rect::new(Point::from_coords(500, 500), Point::from_coords(505, 503));
+        assert_eq!(ids(tree.containing(&small)), brute(&|r| r.contains_rect(&small)));
+        for target in &[Point::from_coords(0, 0), Point::from_coords(512, 256), Point::from_coords(2000, -50)] {
+            let (r, _) = tree.nearest(target).unwrap();
+            let best = rects.iter().map(|e| e.0.distance(target)).fold(f64::INFINITY, f64::min);
+            assert_eq!(r.distance(target), best);
+        }
+    }
+
+    #[test]
+    fn test_r_tree_insert() {
+        let rects = random_rects(1500);
+        let mut tree = RTree::new(8);
+        assert!(tree.nearest(&Point::from_coords(0, 0)).is_none());
+        for &(r, i) in &rects {
+            tree.insert(r, i);
+        }
+        check(&tree, &rects);
+    }
+
+    #[test]
+    fn test_r_tree_bulk_load() {
+        let rects = random_rects(1500);
+        let tree = RTree::bulk_load(rects.clone(), 8);
+        check(&tree, &rects);
+        let mut tree = RTree::bulk_load(rects[..1000].to_vec(), 8);
+        for &(r, i) in &rects[1000..] {
+            tree.insert(r, i);
+        }
+        check(&tree, &rects);
+        let empty: RTree<usize, i64> = RTree::bulk_load(Vec::new(), 8);
+        assert!(empty.is_empty());
+    }
+}
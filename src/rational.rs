@@ -0,0 +1,132 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Neg};
+
+/// An exact fraction num / den, always kept in lowest terms with a positive denominator.
+/// Intermediate results are computed in i128, the reduced result must fit back into i64.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i64,
+    den: i64,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+impl Rational {
+    pub fn new(num: i64, den: i64) -> Rational {
+        assert!(den != 0, "Denominator cannot be zero!");
+        Rational::reduce(num as i128, den as i128)
+    }
+
+    pub fn from_integer(n: i64) -> Rational {
+        Rational { num: n, den: 1 }
+    }
+
+    fn reduce(num: i128, den: i128) -> Rational {
+        let g = gcd(num, den).max(1);
+        let sign = if den < 0 { -1 } else { 1 };
+        let num = sign * num / g;
+        let den = sign * den / g;
+        Rational {
+            num: i64::try_from(num).expect("Rational numerator overflow"),
+            den: i64::try_from(den).expect("Rational denominator overflow"),
+        }
+    }
+
+    pub fn numer(&self) -> i64 {
+        self.num
+    }
+
+    pub fn denom(&self) -> i64 {
+        self.den
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+    fn add(self, rhs: Rational) -> Rational {
+        Rational::reduce(self.num as i128 * rhs.den as i128 + rhs.num as i128 * self.den as i128,
+                         self.den as i128 * rhs.den as i128)
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+    fn sub(self, rhs: Rational) -> Rational {
+        self + (-rhs)
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+    fn mul(self, rhs: Rational) -> Rational {
+        Rational::reduce(self.num as i128 * rhs.num as i128, self.den as i128 * rhs.den as i128)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+    fn neg(self) -> Rational {
+        Rational {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    // denominators are positive, so cross multiplication preserves the order
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (self.num as i128 * other.den as i128).cmp(&(other.num as i128 * self.den as i128))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rational;
+
+    #[test]
+    fn test_rational() {
+        let half = Rational::new(1, 2);
+        let third = Rational::new(-2, -6);
+        assert_eq!(third, Rational::new(1, 3));
+        assert_eq!(half + third, Rational::new(5, 6));
+        assert_eq!(half - third, Rational::new(1, 6));
+        assert_eq!(half * third, Rational::new(1, 6));
+        assert_eq!(-half, Rational::new(1, -2));
+        assert_eq!(Rational::new(4, 2), Rational::from_integer(2));
+        assert!(third < half);
+        assert!(-half < third);
+        assert_eq!(format!("{}", Rational::new(6, -4)), "-3/2");
+        assert_eq!(Rational::new(3, 4).to_f64(), 0.75);
+    }
+}
@@ -0,0 +1,84 @@
+use point::Point;
+use coordinate::Coordinate;
+use definite_num::DefinitelyANumber;
+
+/// An axis-aligned rectangle, closed on all sides.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect<T = DefinitelyANumber> {
+    pub min: Point<T>,
+    pub max: Point<T>,
+}
+
+impl<T> Rect<T> where T: Coordinate {
+    // any two opposite corners
+    pub fn new(p: Point<T>, q: Point<T>) -> Rect<T> {
+        Rect {
+            min: Point::from_coords(p.x.min(q.x), p.y.min(q.y)),
+            max: Point::from_coords(p.x.max(q.x), p.y.max(q.y)),
+        }
+    }
+
+    pub fn contains(&self, p: &Point<T>) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+    }
+
+    pub fn intersects(&self, other: &Rect<T>) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x &&
+        self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+
+    pub fn contains_rect(&self, other: &Rect<T>) -> bool {
+        self.contains(&other.min) && self.contains(&other.max)
+    }
+
+    // smallest rectangle covering both
+    pub fn union(&self, other: &Rect<T>) -> Rect<T> {
+        Rect {
+            min: Point::from_coords(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Point::from_coords(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        (self.max.x.to_f64() - self.min.x.to_f64()) * (self.max.y.to_f64() - self.min.y.to_f64())
+    }
+
+    pub fn center(&self) -> Point<T> {
+        Point::from_coords(self.min.x.midway(self.max.x), self.min.y.midway(self.max.y))
+    }
+
+    // Euclidean distance from the point to the closest point of the rectangle, 0 if inside
+    pub fn distance(&self, p: &Point<T>) -> f64 {
+        let clamp = |v: T, lo: T, hi: T| v.max(lo).min(hi);
+        let closest = Point::from_coords(clamp(p.x, self.min.x, self.max.x), clamp(p.y, self.min.y, self.max.y));
+        closest.distance(p)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use point::Point;
+    use rect::Rect;
+
+    #[test]
+    fn test_rect() {
+        let r = Rect::new(Point::from_coords(4i32, 0), Point::from_coords(0, 2));
+        assert_eq!(r.min, Point::from_coords(0, 0));
+        assert_eq!(r.max, Point::from_coords(4, 2));
+        assert!(r.contains(&Point::from_coords(4, 2)));
+        assert!(r.contains(&Point::from_coords(1, 1)));
+        assert!(!r.contains(&Point::from_coords(5, 1)));
+        assert!(r.intersects(&Rect::new(Point::from_coords(4, 2), Point::from_coords(6, 6))));
+        assert!(!r.intersects(&Rect::new(Point::from_coords(5, 0), Point::from_coords(6, 6))));
+        assert_eq!(r.distance(&Point::from_coords(1, 1)), 0.0);
+        assert_eq!(r.distance(&Point::from_coords(7, 6)), 5.0);
+        assert_eq!(r.distance(&Point::from_coords(2, -3)), 3.0);
+        let inner = Rect::new(Point::from_coords(1, 1), Point::from_coords(4, 2));
+        assert!(r.contains_rect(&inner));
+        assert!(!inner.contains_rect(&r));
+        let other = Rect::new(Point::from_coords(5, 5), Point::from_coords(6, 7));
+        assert_eq!(r.union(&other), Rect::new(Point::from_coords(0, 0), Point::from_coords(6, 7)));
+        assert_eq!(r.area(), 8.0);
+        assert_eq!(r.center(), Point::from_coords(2, 1));
+    }
+}
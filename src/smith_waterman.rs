@@ -0,0 +1,100 @@
+use needleman_wunsch::{AlignMatrix, Direction, ResultEntry, traceback};
+use alignment::Alignment;
+
+/// Result of a local alignment. `start` and `end` are the half-open ranges of the aligned
+/// region in both inputs, i.e. `seq1[start.0..end.0]` is aligned to `seq2[start.1..end.1]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalAlignment<T> {
+	pub alignment: Alignment<T>,
+	pub start: (usize, usize),
+	pub end: (usize, usize),
+}
+
+pub trait SmithWaterman<T> {
+	fn local_align(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> LocalAlignment<T>;
+}
+
+impl<T> SmithWaterman<T> for [T] where T: Eq + Copy + Clone {
+	fn local_align(&self, vec: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> LocalAlignment<T> {
+		let mut mat = init_local_matrix(self.len(), vec.len());
+		let (i, j) = fill_local_matrix(&mut mat, self, vec, similarity, gap_penalty);
+		let (seq1, seq2, start) = traceback(&mat, self, vec, i, j);
+		LocalAlignment {
+			alignment: Alignment::from_pair(seq1, seq2, mat[i][j].score),
+			start,
+			end: (i, j),
+		}
+	}
+}
+
+/// Every cell starts out as a possible start of the local alignment: score 0, undefined direction.
+fn init_local_matrix(len1: usize, len2: usize) -> AlignMatrix {
+	vec![vec![ResultEntry::new(0, Direction::Undefined); len2 + 1]; len1 + 1]
+}
+
+/// Fill the matrix with scores floored at zero. A cell whose best score is 0 keeps the undefined
+/// direction, so the traceback stops there. Returns the position of the first maximum cell.
+fn fill_local_matrix<T>(mat: &mut AlignMatrix, vec1: &[T], vec2: &[T], similarity: &dyn Fn(&T, &T) -> i64, gap_penalty: i64) -> (usize, usize) {
+	assert_eq!(mat.len(), vec1.len() + 1);
+	assert_eq!(mat[0].len(), vec2.len() + 1);
+	let mut best = (0, 0);
+	for i in 1..(vec1.len() + 1) {
+		for j in 1..(vec2.len() + 1) {
+			let match_score = mat[i-1][j-1].score + similarity(&vec1[i-1], &vec2[j-1]);
+			let up_score = mat[i-1][j].score + gap_penalty;
+			let left_score = mat[i][j-1].score + gap_penalty;
+			let m = *[match_score, up_score, left_score].iter().max().unwrap();
+			mat[i][j] = if m <= 0 {
+				ResultEntry::new(0, Direction::Undefined)
+			} else if m == match_score {
+				ResultEntry::new(m, Direction::Match)
+			} else if m == up_score {
+				ResultEntry::new(m, Direction::Up)
+			} else {
+				ResultEntry::new(m, Direction::Left)
+			};
+			if mat[i][j].score > mat[best.0][best.1].score {
+				best = (i, j);
+			}
+		}
+	}
+	best
+}
+
+#[cfg(test)]
+mod test {
+	use super::SmithWaterman;
+
+	fn similarity(c1: &char, c2: &char) -> i64 {
+		if c1 == c2 { 3 } else { -3 }
+	}
+
+	#[test]
+	fn test_local_align() {
+		let vec1: Vec<_> = "TGTTACGG".chars().collect();
+		let vec2: Vec<_> = "GGTTGACTA".chars().collect();
+		let res = vec1.local_align(&vec2, &similarity, -2);
+		assert_eq!(res.alignment.score, 13);
+		assert_eq!(res.alignment.seq1(), vec![Some('G'), Some('T'), Some('T'), None, Some('A'), Some('C')]);
+		assert_eq!(res.alignment.seq2(), vec![Some('G'), Some('T'), Some('T'), Some('G'), Some('A'), Some('C')]);
+		assert_eq!(res.start, (1, 1));
+		assert_eq!(res.end, (6, 7));
+		assert_eq!(&vec1[res.start.0..res.end.0], &['G', 'T', 'T', 'A', 'C']);
+		assert_eq!(&vec2[res.start.1..res.end.1], &['G', 'T', 'T', 'G', 'A', 'C']);
+	}
+
+	#[test]
+	fn test_local_align_motif() {
+		let read: Vec<_> = "xxxxxxxxmotifyyyyyyyy".chars().collect();
+		let motif: Vec<_> = "motif".chars().collect();
+		let res = motif.local_align(&read, &similarity, -2);
+		assert_eq!(res.alignment.score, 15);
+		assert_eq!(res.start, (0, 8));
+		assert_eq!(res.end, (5, 13));
+		// nothing in common: empty alignment with score 0
+		let res = motif.local_align(&['a', 'b'], &similarity, -2);
+		assert_eq!(res.alignment.score, 0);
+		assert!(res.alignment.is_empty());
+		assert_eq!(res.start, res.end);
+	}
+}
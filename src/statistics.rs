@@ -0,0 +1,391 @@
+//! Karlin-Altschul statistics of local alignment scores. For random sequences with the given
+//! residue frequencies the number of distinct local alignments scoring at least S between
+//! sequences of lengths m and n is expected to be E = K m n e^(-lambda S). lambda and K
+//! follow from the scoring scheme; the theory is exact for ungapped alignments. For gapped
+//! alignments the ungapped parameters are only an approximation, published gapped values can
+//! be used by filling in the struct directly.
+use std::fmt;
+use std::hash::Hash;
+use std::collections::HashMap;
+
+use alignment::Alignment;
+
+/// Terms of the series for K. The terms decay geometrically, BLAST uses the same limit.
+const K_ITERATIONS: usize = 100;
+
+/// Multiply-adds the convolutions of the series for K may take. The distribution of S_k is
+/// k times as wide as the scores, so a wide range of scores ends the series early.
+const K_MAX_WORK: usize = 1 << 26;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatisticsError {
+	// the frequencies are empty, negative or sum to zero
+	Frequencies(String),
+	// the expected score of a random pair, which must be negative
+	ExpectedScore(f64),
+	// no pair of residues has a positive score
+	NoPositiveScore,
+}
+
+impl fmt::Display for StatisticsError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			StatisticsError::Frequencies(ref msg) => write!(f, "invalid residue frequencies: {}", msg),
+			StatisticsError::ExpectedScore(e) => write!(f, "expected score {} is not negative", e),
+			StatisticsError::NoPositiveScore => write!(f, "no pair of residues scores positive"),
+		}
+	}
+}
+
+/// Parameters of the extreme value distribution of local alignment scores. `h` is the
+/// relative entropy of the scoring scheme in nats per aligned pair.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KarlinAltschul {
+	pub lambda: f64,
+	pub k: f64,
+	pub h: f64,
+}
+
+/// Score of an alignment together with its normalized bit score and E-value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AlignmentStats {
+	pub score: i64,
+	pub bit_score: f64,
+	pub evalue: f64,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+	if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+/// Relative frequencies of the elements in the sequences, in order of first occurrence.
+pub fn frequencies<T: Eq + Hash + Copy>(seqs: &[&[T]]) -> Vec<(T, f64)> {
+	let mut counts: Vec<(T, usize)> = Vec::new();
+	let mut index: HashMap<T, usize> = HashMap::new();
+	for &x in seqs.iter().flat_map(|s| s.iter()) {
+		let i = *index.entry(x).or_insert_with(|| {
+			counts.push((x, 0));
+			counts.len() - 1
+		});
+		counts[i].1 += 1;
+	}
+	let total: usize = counts.iter().map(|&(_, c)| c).sum();
+	counts.into_iter().map(|(x, c)| (x, c as f64 / total as f64)).collect()
+}
+
+impl KarlinAltschul {
+	/// Parameters for the scores of the similarity function when both sequences are drawn
+	/// from the residue frequencies, which are normalized to sum to one. K may be approximate,
+	/// see `from_distribution`.
+	pub fn from_scores<T>(frequencies: &[(T, f64)], similarity: &dyn Fn(&T, &T) -> i64) -> Result<KarlinAltschul, StatisticsError> {
+		if frequencies.is_empty() {
+			return Err(StatisticsError::Frequencies("no residues".to_string()));
+		}
+		let total = total_probability(frequencies.iter().map(|&(_, p)| p))?;
+		let mut distribution: Vec<(i64, f64)> = Vec::new();
+		for &(ref a, p) in frequencies {
+			for &(ref b, q) in frequencies {
+				distribution.push((similarity(a, b), p * q / (total * total)));
+			}
+		}
+		KarlinAltschul::from_distribution(&distribution)
+	}
+
+	/// Parameters for a distribution of pair scores given as (score, probability). Scores may
+	/// repeat, their probabilities add up and are normalized to sum to one.
+	///
+	/// lambda and H are exact up to rounding. K is exact when the highest score or the lowest
+	/// one is the greatest common divisor of the scores (or its negative), otherwise it comes
+	/// from a series which is cut short without notice: after 100 terms, which leaves a
+	/// visible error when the expected score is close to zero, or once the convolutions take
+	/// 2^26 steps, which a wide range of scores reaches within fewer terms.
+	pub fn from_distribution(distribution: &[(i64, f64)]) -> Result<KarlinAltschul, StatisticsError> {
+		if distribution.is_empty() {
+			return Err(StatisticsError::Frequencies("no scores".to_string()));
+		}
+		let total = total_probability(distribution.iter().map(|&(_, p)| p))?;
+		let support: Vec<(i64, f64)> = distribution.iter().filter(|&&(_, p)| p > 0.0).map(|&(s, p)| (s, p / total)).collect();
+		let expected: f64 = support.iter().map(|&(s, p)| s as f64 * p).sum();
+		if expected >= 0.0 {
+			return Err(StatisticsError::ExpectedScore(expected));
+		}
+		if support.iter().all(|&(s, _)| s <= 0) {
+			return Err(StatisticsError::NoPositiveScore);
+		}
+		let lambda = solve_lambda(&support);
+		let h = lambda * support.iter().map(|&(s, p)| s as f64 * p * (lambda * s as f64).exp()).sum::<f64>();
+		let k = solve_k(&support, lambda, h);
+		Ok(KarlinAltschul { lambda, k, h })
+	}
+
+	/// Score in bits, comparable across scoring schemes: (lambda S - ln K) / ln 2.
+	pub fn bit_score(&self, score: i64) -> f64 {
+		(self.lambda * score as f64 - self.k.ln()) / ::std::f64::consts::LN_2
+	}
+
+	/// Expected number of alignments scoring at least `score` between random sequences of
+	/// lengths m and n.
+	pub fn evalue(&self, score: i64, m: usize, n: usize) -> f64 {
+		self.k * m as f64 * n as f64 * (-self.lambda * score as f64).exp()
+	}
+
+	/// Probability of at least one alignment scoring at least `score`, 1 - e^(-E).
+	pub fn pvalue(&self, score: i64, m: usize, n: usize) -> f64 {
+		-(-self.evalue(score, m, n)).exp_m1()
+	}
+
+	/// Statistics of an alignment found between sequences of lengths m and n.
+	pub fn stats<T>(&self, alignment: &Alignment<T>, m: usize, n: usize) -> AlignmentStats {
+		AlignmentStats {
+			score: alignment.score,
+			bit_score: self.bit_score(alignment.score),
+			evalue: self.evalue(alignment.score, m, n),
+		}
+	}
+}
+
+/// Sum of the probabilities, an error if one of them is negative or NaN or if all are zero.
+fn total_probability<I: Iterator<Item = f64>>(probabilities: I) -> Result<f64, StatisticsError> {
+	let mut total = 0.0;
+	for p in probabilities {
+		if p < 0.0 || p.is_nan() {
+			return Err(StatisticsError::Frequencies(format!("{} is not a probability", p)));
+		}
+		total += p;
+	}
+	if total <= 0.0 {
+		return Err(StatisticsError::Frequencies("the probabilities sum to zero".to_string()));
+	}
+	Ok(total)
+}
+
+/// The unique positive root of sum p(s) e^(lambda s) = 1. The left side is convex, 1 at zero
+/// and decreasing there because the expected score is negative, so bisection between zero
+/// and a point where it exceeds 1 finds the root.
+fn solve_lambda(support: &[(i64, f64)]) -> f64 {
+	let f = |lambda: f64| support.iter().map(|&(s, p)| p * (lambda * s as f64).exp()).sum::<f64>() - 1.0;
+	let mut high = 0.5;
+	while f(high) <= 0.0 {
+		high *= 2.0;
+	}
+	let mut low = 0.0;
+	for _ in 0..200 {
+		let mid = (low + high) / 2.0;
+		if f(mid) > 0.0 {
+			high = mid;
+		} else {
+			low = mid;
+		}
+	}
+	(low + high) / 2.0
+}
+
+/// Greatest common divisor d of the scores and the lowest and highest score in units of d.
+fn lattice(support: &[(i64, f64)]) -> (i64, i64, i64) {
+	let d = support.iter().fold(0, |g, &(s, _)| gcd(g, s));
+	let low = support.iter().map(|&(s, _)| s).min().unwrap() / d;
+	let high = support.iter().map(|&(s, _)| s).max().unwrap() / d;
+	(d, low, high)
+}
+
+/// K in closed form if there is one, as in BLAST, otherwise from the series.
+fn solve_k(support: &[(i64, f64)], lambda: f64, h: f64) -> f64 {
+	solve_k_skip_free(support, lambda, h).unwrap_or_else(|| solve_k_series(support, lambda, h, K_MAX_WORK).0)
+}
+
+/// K from the series of Karlin and Altschul (1990) over the sums S_k of k pair scores:
+/// K = lambda d e^(-2 sigma) / (H (1 - e^(-lambda d))) with
+/// sigma = sum over k of (E[e^(lambda S_k); S_k < 0] + P(S_k >= 0)) / k,
+/// where d is the greatest common divisor of the scores. The series ends when its terms are
+/// negligible, after `K_ITERATIONS` terms or before the convolutions take more than
+/// `max_work` multiply-adds, whichever comes first. Returns K and the number of terms.
+fn solve_k_series(support: &[(i64, f64)], lambda: f64, h: f64, max_work: usize) -> (f64, usize) {
+	let (d, low, high) = lattice(support);
+	let width = (high - low) as usize;
+	let mut step = vec![0.0; width + 1];
+	for &(s, p) in support {
+		step[(s / d - low) as usize] += p;
+	}
+	// only the scores that occur take part in the convolution
+	let step: Vec<(usize, f64)> = step.into_iter().enumerate().filter(|&(_, q)| q > 0.0).collect();
+	// distribution of S_k / d, index i is the score (i + k low) d
+	let mut sum = vec![1.0];
+	let mut sigma = 0.0;
+	let mut work = 0;
+	let mut terms = 0;
+	for k in 1..(K_ITERATIONS + 1) {
+		work += sum.len() * step.len();
+		if work > max_work {
+			break;
+		}
+		let mut next = vec![0.0; sum.len() + width];
+		for (i, &p) in sum.iter().enumerate() {
+			if p == 0.0 {
+				continue;
+			}
+			for &(j, q) in &step {
+				next[i + j] += p * q;
+			}
+		}
+		sum = next;
+		let mut term = 0.0;
+		for (i, &p) in sum.iter().enumerate() {
+			let score = (i as i64 + k as i64 * low) * d;
+			term += if score < 0 { p * (lambda * score as f64).exp() } else { p };
+		}
+		sigma += term / k as f64;
+		terms = k;
+		if term / (k as f64) < 1e-14 * sigma {
+			break;
+		}
+	}
+	let d = d as f64;
+	(lambda * d * (-2.0 * sigma).exp() / (h * (1.0 - (-lambda * d).exp())), terms)
+}
+
+/// K when the walk of the scores, in units of d, moves up or down by at most one step: then
+/// the ladder heights in that direction are always one and the series sums up. With l the
+/// lambda of the scores in units of d and the mean score mu / d, K = H (1 - e^(-l)) / l for a
+/// highest score of d, K = l (mu / d)^2 (1 - e^(-l)) / H for a lowest one of -d, and both
+/// agree on (p(-d) - p(d))^2 / p(-d) when the scores are -d and d only.
+fn solve_k_skip_free(support: &[(i64, f64)], lambda: f64, h: f64) -> Option<f64> {
+	let (d, low, high) = lattice(support);
+	let lambda = lambda * d as f64;
+	if low == -1 && high == 1 {
+		let prob = |score: i64| support.iter().filter(|&&(s, _)| s == score * d).map(|&(_, p)| p).sum::<f64>();
+		let (down, up) = (prob(-1), prob(1));
+		Some((down - up) * (down - up) / down)
+	} else if high == 1 {
+		Some(h * -(-lambda).exp_m1() / lambda)
+	} else if low == -1 {
+		let mean = support.iter().map(|&(s, p)| s as f64 * p).sum::<f64>() / d as f64;
+		Some(lambda * mean * mean * -(-lambda).exp_m1() / h)
+	} else {
+		None
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{KarlinAltschul, StatisticsError, K_ITERATIONS, K_MAX_WORK, frequencies, solve_k_series, solve_k_skip_free};
+	use smith_waterman::SmithWaterman;
+	use substitution_matrix::SubstitutionMatrix;
+	use test_support::random_dna;
+
+	// Robinson and Robinson (1991) amino acid frequencies, the background of BLAST
+	const ROBINSON: [(u8, f64); 20] = [
+		(b'A', 0.07805), (b'R', 0.05129), (b'N', 0.04487), (b'D', 0.05364), (b'C', 0.01925),
+		(b'Q', 0.04264), (b'E', 0.06295), (b'G', 0.07377), (b'H', 0.02199), (b'I', 0.05142),
+		(b'L', 0.09019), (b'K', 0.05744), (b'M', 0.02243), (b'F', 0.03856), (b'P', 0.05203),
+		(b'S', 0.07120), (b'T', 0.05841), (b'W', 0.01330), (b'Y', 0.03216), (b'V', 0.06441),
+	];
+
+	fn assert_close(x: f64, expected: f64, tolerance: f64) {
+		assert!((x - expected).abs() <= tolerance, "{} is not {} +- {}", x, expected, tolerance);
+	}
+
+	#[test]
+	fn test_plus_minus_one() {
+		// a match has probability 1/4: lambda = ln 3 and, scores being +-1, K = (q - p)^2 / q
+		let params = KarlinAltschul::from_distribution(&[(1, 0.25), (-1, 0.75)]).unwrap();
+		assert_close(params.lambda, 3f64.ln(), 1e-12);
+		assert_close(params.k, (0.75 - 0.25f64).powi(2) / 0.75, 1e-12);
+		let dna = SubstitutionMatrix::dna_identity();
+		let freqs = frequencies(&[b"ACGT"]);
+		let same = KarlinAltschul::from_scores(&freqs, &dna.similarity()).unwrap();
+		assert_close(same.lambda, params.lambda, 1e-12);
+		assert_close(same.k, params.k, 1e-12);
+		// scaling the scores scales lambda but leaves K alone
+		let scaled = KarlinAltschul::from_distribution(&[(2, 0.25), (-2, 0.75)]).unwrap();
+		assert_close(scaled.lambda, params.lambda / 2.0, 1e-12);
+		assert_close(scaled.k, params.k, 1e-9);
+	}
+
+	#[test]
+	fn test_blosum62() {
+		// the ungapped values BLAST reports for BLOSUM62
+		let blosum = SubstitutionMatrix::blosum62();
+		let params = KarlinAltschul::from_scores(&ROBINSON, &blosum.similarity()).unwrap();
+		assert_close(params.lambda, 0.3176, 5e-4);
+		assert_close(params.k, 0.134, 2e-3);
+		assert_close(params.h, 0.401, 2e-3);
+		let bits = params.bit_score(50);
+		assert_close(params.evalue(50, 300, 1_000_000), 300.0 * 1e6 * 2f64.powf(-bits), 1e-9);
+		assert!(params.pvalue(50, 300, 1_000_000) <= params.evalue(50, 300, 1_000_000));
+	}
+
+	#[test]
+	fn test_skip_free() {
+		// the closed forms agree with the series: highest score 1, lowest score -1, and
+		// highest score 3 with a divisor of 3
+		for support in &[vec![(1, 0.2), (0, 0.1), (-2, 0.4), (-3, 0.3)], vec![(2, 0.05), (-1, 0.9), (3, 0.05)], vec![(3, 0.2), (-3, 0.5), (-6, 0.3)]] {
+			let params = KarlinAltschul::from_distribution(support).unwrap();
+			assert!(solve_k_skip_free(support, params.lambda, params.h).is_some());
+			assert_close(params.k, solve_k_series(support, params.lambda, params.h, K_MAX_WORK).0, 1e-6);
+		}
+		// a mismatch a thousand times worse than a match: the distribution of S_k spans 1001 k
+		// scores, the closed form needs no series
+		let support = [(1, 0.25), (-1000, 0.75)];
+		let params = KarlinAltschul::from_distribution(&support).unwrap();
+		assert_eq!(solve_k_skip_free(&support, params.lambda, params.h), Some(params.k));
+		assert!(params.k > 0.0 && params.k < 1.0);
+	}
+
+	#[test]
+	fn test_series_work() {
+		// scores +-1: term k convolves 2k - 1 sums with 2 scores, k terms take 2 k^2 steps
+		let support = [(1, 0.25), (-1, 0.75)];
+		let params = KarlinAltschul::from_distribution(&support).unwrap();
+		assert_eq!(solve_k_series(&support, params.lambda, params.h, 200).1, 10);
+		assert_eq!(solve_k_series(&support, params.lambda, params.h, 199).1, 9);
+		// without a limit on the work the terms, which decay like 0.75^k, run to K_ITERATIONS
+		let (k, terms) = solve_k_series(&support, params.lambda, params.h, usize::MAX);
+		assert_eq!(terms, K_ITERATIONS);
+		assert_close(k, params.k, 1e-6);
+	}
+
+	#[test]
+	fn test_invalid() {
+		let dna = SubstitutionMatrix::identity(b"ACGT", 1, 1);
+		assert_eq!(KarlinAltschul::from_scores(&frequencies(&[b"ACGT"]), &dna.similarity()), Err(StatisticsError::ExpectedScore(1.0)));
+		assert_eq!(KarlinAltschul::from_distribution(&[(0, 0.5), (-1, 0.5)]), Err(StatisticsError::NoPositiveScore));
+		match KarlinAltschul::from_scores(&[(b'A', -0.5), (b'C', 1.5)], &SubstitutionMatrix::dna_identity().similarity()) {
+			Err(StatisticsError::Frequencies(_)) => {},
+			other => panic!("unexpected {:?}", other),
+		}
+		assert!(KarlinAltschul::from_scores::<u8>(&[], &|_, _| 1).is_err());
+		for distribution in &[vec![], vec![(1, 0.0), (-1, 0.0)], vec![(1, 0.5), (-1, -0.5), (-2, 1.0)], vec![(1, f64::NAN), (-1, 0.5)]] {
+			match KarlinAltschul::from_distribution(distribution) {
+				Err(StatisticsError::Frequencies(_)) => {},
+				other => panic!("unexpected {:?} for {:?}", other, distribution),
+			}
+		}
+		// probabilities that do not sum to one are normalized like the residue frequencies
+		let params = KarlinAltschul::from_distribution(&[(1, 0.25), (-1, 0.75)]).unwrap();
+		let scaled = KarlinAltschul::from_distribution(&[(1, 0.5), (-1, 1.5)]).unwrap();
+		assert_close(scaled.lambda, params.lambda, 1e-12);
+		assert_close(scaled.k, params.k, 1e-12);
+		assert_close(scaled.h, params.h, 1e-12);
+	}
+
+	#[test]
+	fn test_alignment_stats() {
+		let dna = SubstitutionMatrix::identity(b"ACGT", 1, -3);
+		let params = KarlinAltschul::from_scores(&frequencies(&[b"ACGT"]), &dna.similarity()).unwrap();
+		// a 40 long motif shared by two unrelated sequences is significant, the best local
+		// alignment of the unrelated parts alone is not
+		let motif = random_dna(40, 1);
+		let mut seq1 = random_dna(300, 2);
+		let mut seq2 = random_dna(300, 3);
+		let unrelated = seq1[..].local_align(&seq2, &dna.similarity(), -5);
+		let stats = params.stats(&unrelated.alignment, seq1.len(), seq2.len());
+		assert!(stats.evalue > 1e-2, "{:?}", stats);
+		seq1.splice(100..100, motif.iter().cloned());
+		seq2.splice(200..200, motif.iter().cloned());
+		let found = seq1[..].local_align(&seq2, &dna.similarity(), -5);
+		let stats = params.stats(&found.alignment, seq1.len(), seq2.len());
+		assert!(stats.score >= 40);
+		assert!(stats.evalue < 1e-10, "{:?}", stats);
+		assert_close(stats.bit_score, params.bit_score(stats.score), 0.0);
+	}
+}
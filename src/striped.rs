@@ -0,0 +1,345 @@
+//! Score-only Smith-Waterman with affine gaps using Farrar's striped SIMD layout, for scanning
+//! many database sequences with one query. The query is split into segments that are
+//! processed in parallel by the lanes of a vector: lane l holds query positions
+//! l * seg_len .. (l + 1) * seg_len, so the dependency along the query only crosses lanes once
+//! per column. Vertical gaps are first ignored and then fixed up by the "lazy F" loop, which
+//! usually ends after a few iterations.
+//!
+//! On x86_64 the scores are computed with SSE2 (part of the x86_64 baseline) in 16 u8 lanes,
+//! redone in 8 i16 lanes if they overflow, and in i64 by the scalar fallback if those overflow
+//! too. Other targets only use the scalar fallback. A gap of length k scores
+//! gap_open + (k - 1) * gap_extend like in `Gotoh`.
+
+/// A query prepared for striped alignment against many database sequences of bytes.
+pub struct StripedProfile {
+	len: usize,
+	// score of query position q against byte c at c * len + q
+	table: Vec<i64>,
+	gap_open: i64,
+	gap_extend: i64,
+	min_score: i64,
+	max_score: i64,
+	// the striped profiles and their segment lengths, None where the values do not fit
+	profile_u8: Option<(Vec<u8>, usize)>,
+	profile_i16: Option<(Vec<i16>, usize)>,
+}
+
+impl StripedProfile {
+	pub fn new(query: &[u8], similarity: &dyn Fn(&u8, &u8) -> i64, gap_open: i64, gap_extend: i64) -> StripedProfile {
+		let mut table = Vec::with_capacity(256 * query.len());
+		for c in 0..256 {
+			let c = c as u8;
+			table.extend(query.iter().map(|q| similarity(q, &c)));
+		}
+		let mut profile = StripedProfile {
+			len: query.len(),
+			min_score: table.iter().cloned().min().unwrap_or(0),
+			max_score: table.iter().cloned().max().unwrap_or(0),
+			table,
+			gap_open,
+			gap_extend,
+			profile_u8: None,
+			profile_i16: None,
+		};
+		if cfg!(target_arch = "x86_64") && gap_open <= 0 && gap_extend <= 0 {
+			let bias = profile.bias();
+			if profile.max_score + bias <= 255 && -gap_open <= 255 && -gap_extend <= 255 {
+				profile.profile_u8 = Some(profile.striped(16, 0, &|s| (s + bias) as u8));
+			}
+			let limit = i16::MAX as i64;
+			if profile.max_score <= limit && profile.min_score >= -limit && -gap_open <= limit && -gap_extend <= limit {
+				profile.profile_i16 = Some(profile.striped(8, i16::MIN, &|s| s as i16));
+			}
+		}
+		profile
+	}
+
+	fn bias(&self) -> i64 {
+		-self.min_score.min(0)
+	}
+
+	fn score(&self, q: usize, c: u8) -> i64 {
+		self.table[c as usize * self.len + q]
+	}
+
+	/// Best local alignment score of the query against `db`.
+	pub fn local_score(&self, db: &[u8]) -> i64 {
+		#[cfg(target_arch = "x86_64")]
+		{
+			if let Some(score) = self.local_score_u8(db) {
+				return score;
+			}
+			if let Some(score) = self.local_score_i16(db) {
+				return score;
+			}
+		}
+		self.local_score_scalar(db)
+	}
+
+	/// The same score computed one cell at a time with i64, Gotoh's recurrences floored at zero.
+	pub fn local_score_scalar(&self, db: &[u8]) -> i64 {
+		// a gap can always be opened from a cell scoring 0, so this is low enough
+		let neg_inf = self.gap_open.min(0) * 2 - 1;
+		let mut h = vec![0; self.len + 1];
+		let mut e = vec![neg_inf; self.len + 1];
+		let mut best = 0;
+		for &c in db {
+			let mut diag = 0;
+			let mut up = 0;
+			let mut f = neg_inf;
+			for i in 1..(self.len + 1) {
+				e[i] = (e[i] + self.gap_extend).max(h[i] + self.gap_open);
+				f = (f + self.gap_extend).max(up + self.gap_open);
+				let cell = (diag + self.score(i - 1, c)).max(e[i]).max(f).max(0);
+				diag = h[i];
+				h[i] = cell;
+				up = cell;
+				best = best.max(cell);
+			}
+		}
+		best
+	}
+
+	/// Lane l of segment k holds query position k + l * seg_len, padding scores `pad`.
+	fn striped<X: Copy>(&self, lanes: usize, pad: X, convert: &dyn Fn(i64) -> X) -> (Vec<X>, usize) {
+		let seg_len = self.len.div_ceil(lanes).max(1);
+		let mut profile = Vec::with_capacity(256 * seg_len * lanes);
+		for c in 0..256 {
+			for k in 0..seg_len {
+				for l in 0..lanes {
+					let q = k + l * seg_len;
+					profile.push(if q < self.len { convert(self.score(q, c as u8)) } else { pad });
+				}
+			}
+		}
+		(profile, seg_len)
+	}
+
+	/// None if some value does not fit into u8 lanes or the score may have saturated.
+	#[cfg(target_arch = "x86_64")]
+	fn local_score_u8(&self, db: &[u8]) -> Option<i64> {
+		let (ref profile, seg_len) = *self.profile_u8.as_ref()?;
+		let bias = self.bias();
+		let max = unsafe { sse2::local_score_u8(profile, seg_len, db, bias as u8, -self.gap_open as u8, -self.gap_extend as u8) } as i64;
+		if max + bias >= 255 { None } else { Some(max) }
+	}
+
+	/// None if some value does not fit into i16 lanes or the score may have saturated.
+	#[cfg(target_arch = "x86_64")]
+	fn local_score_i16(&self, db: &[u8]) -> Option<i64> {
+		let (ref profile, seg_len) = *self.profile_i16.as_ref()?;
+		let limit = i16::MAX as i64;
+		let max = unsafe { sse2::local_score_i16(profile, seg_len, db, -self.gap_open as i16, -self.gap_extend as i16) } as i64;
+		if max + self.max_score.max(0) >= limit { None } else { Some(max) }
+	}
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+	use std::arch::x86_64::*;
+
+	unsafe fn load(profile: &[i16], offset: usize) -> __m128i {
+		_mm_loadu_si128(profile[offset..offset + 8].as_ptr() as *const __m128i)
+	}
+
+	unsafe fn load_u8(profile: &[u8], offset: usize) -> __m128i {
+		_mm_loadu_si128(profile[offset..offset + 16].as_ptr() as *const __m128i)
+	}
+
+	/// Farrar's algorithm in 8 signed saturating lanes, `gap_open` and `gap_extend` are costs.
+	pub unsafe fn local_score_i16(profile: &[i16], seg_len: usize, db: &[u8], gap_open: i16, gap_extend: i16) -> i16 {
+		let zero = _mm_setzero_si128();
+		let v_open = _mm_set1_epi16(gap_open);
+		let v_extend = _mm_set1_epi16(gap_extend);
+		// shifting F down a lane brings in -infinity, 0 would keep the lazy F loop going
+		let v_first = _mm_insert_epi16(zero, i16::MIN as i32, 0);
+		let mut h_load = vec![zero; seg_len];
+		let mut h_store = vec![zero; seg_len];
+		let mut e = vec![zero; seg_len];
+		let mut v_max = zero;
+		for &c in db {
+			let offset = c as usize * seg_len * 8;
+			let mut v_f = zero;
+			// the diagonal of the first segment: the previous column's last segment, one lane down
+			let mut v_h = _mm_slli_si128(h_store[seg_len - 1], 2);
+			::std::mem::swap(&mut h_load, &mut h_store);
+			for k in 0..seg_len {
+				v_h = _mm_adds_epi16(v_h, load(profile, offset + k * 8));
+				v_h = _mm_max_epi16(v_h, e[k]);
+				v_h = _mm_max_epi16(v_h, v_f);
+				v_h = _mm_max_epi16(v_h, zero);
+				v_max = _mm_max_epi16(v_max, v_h);
+				h_store[k] = v_h;
+				v_h = _mm_subs_epi16(v_h, v_open);
+				e[k] = _mm_max_epi16(_mm_subs_epi16(e[k], v_extend), v_h);
+				v_f = _mm_max_epi16(_mm_subs_epi16(v_f, v_extend), v_h);
+				v_h = h_load[k];
+			}
+			// lazy F: carry vertical gaps across the lanes until they cannot raise a cell
+			let mut k = 0;
+			v_f = _mm_or_si128(_mm_slli_si128(v_f, 2), v_first);
+			while _mm_movemask_epi8(_mm_cmpgt_epi16(v_f, _mm_subs_epi16(h_store[k], v_open))) != 0 {
+				h_store[k] = _mm_max_epi16(h_store[k], v_f);
+				v_max = _mm_max_epi16(v_max, h_store[k]);
+				e[k] = _mm_max_epi16(e[k], _mm_subs_epi16(h_store[k], v_open));
+				v_f = _mm_subs_epi16(v_f, v_extend);
+				k += 1;
+				if k == seg_len {
+					k = 0;
+					v_f = _mm_or_si128(_mm_slli_si128(v_f, 2), v_first);
+				}
+			}
+		}
+		let mut lanes = [0i16; 8];
+		_mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, v_max);
+		*lanes.iter().max().unwrap()
+	}
+
+	/// The same in 16 unsigned saturating lanes. The profile holds scores plus `bias`, which
+	/// is subtracted right after adding, saturation at 0 does the flooring of local alignment.
+	pub unsafe fn local_score_u8(profile: &[u8], seg_len: usize, db: &[u8], bias: u8, gap_open: u8, gap_extend: u8) -> u8 {
+		let zero = _mm_setzero_si128();
+		let v_bias = _mm_set1_epi8(bias as i8);
+		let v_open = _mm_set1_epi8(gap_open as i8);
+		let v_extend = _mm_set1_epi8(gap_extend as i8);
+		let mut h_load = vec![zero; seg_len];
+		let mut h_store = vec![zero; seg_len];
+		let mut e = vec![zero; seg_len];
+		let mut v_max = zero;
+		for &c in db {
+			let offset = c as usize * seg_len * 16;
+			let mut v_f = zero;
+			let mut v_h = _mm_slli_si128(h_store[seg_len - 1], 1);
+			::std::mem::swap(&mut h_load, &mut h_store);
+			for k in 0..seg_len {
+				v_h = _mm_adds_epu8(v_h, load_u8(profile, offset + k * 16));
+				v_h = _mm_subs_epu8(v_h, v_bias);
+				v_h = _mm_max_epu8(v_h, e[k]);
+				v_h = _mm_max_epu8(v_h, v_f);
+				v_max = _mm_max_epu8(v_max, v_h);
+				h_store[k] = v_h;
+				v_h = _mm_subs_epu8(v_h, v_open);
+				e[k] = _mm_max_epu8(_mm_subs_epu8(e[k], v_extend), v_h);
+				v_f = _mm_max_epu8(_mm_subs_epu8(v_f, v_extend), v_h);
+				v_h = h_load[k];
+			}
+			// there is no unsigned comparison, a lane of v_f is larger iff the saturating
+			// difference is not zero
+			let mut k = 0;
+			v_f = _mm_slli_si128(v_f, 1);
+			while _mm_movemask_epi8(_mm_cmpeq_epi8(_mm_subs_epu8(v_f, _mm_subs_epu8(h_store[k], v_open)), zero)) != 0xFFFF {
+				h_store[k] = _mm_max_epu8(h_store[k], v_f);
+				v_max = _mm_max_epu8(v_max, h_store[k]);
+				e[k] = _mm_max_epu8(e[k], _mm_subs_epu8(h_store[k], v_open));
+				v_f = _mm_subs_epu8(v_f, v_extend);
+				k += 1;
+				if k == seg_len {
+					k = 0;
+					v_f = _mm_slli_si128(v_f, 1);
+				}
+			}
+		}
+		let mut lanes = [0u8; 16];
+		_mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, v_max);
+		*lanes.iter().max().unwrap()
+	}
+}
+
+#[cfg(test)]
+const PROTEIN: &[u8] = b"ARNDCQEGHILKMFPSTWYV";
+
+#[cfg(test)]
+mod test {
+	use super::{StripedProfile, PROTEIN};
+	use test_support::random_seq;
+	use smith_waterman::SmithWaterman;
+	use substitution_matrix::SubstitutionMatrix;
+
+	fn dna_similarity(a: &u8, b: &u8) -> i64 {
+		if a == b { 2 } else { -3 }
+	}
+
+	#[test]
+	fn test_scalar_matches_smith_waterman() {
+		for seed in 0..10 {
+			let query = random_seq(b"ACGT", 40 + seed as usize, seed);
+			let db = random_seq(b"ACGT", 70, seed + 20);
+			let profile = StripedProfile::new(&query, &dna_similarity, -2, -2);
+			assert_eq!(profile.local_score_scalar(&db), query.local_align(&db, &dna_similarity, -2).alignment.score);
+		}
+	}
+
+	#[test]
+	fn test_striped_matches_scalar() {
+		let blosum62 = SubstitutionMatrix::blosum62();
+		let similarity = blosum62.similarity();
+		// query lengths around the lane counts, including a single short segment
+		for &len in &[1, 7, 16, 17, 33, 100, 250] {
+			for seed in 0..4 {
+				let query = random_seq(PROTEIN, len, seed * 31 + len as u64);
+				let db = random_seq(PROTEIN, 300, seed + 5);
+				for &(open, extend) in &[(-11, -1), (-4, -4), (-1, -1)] {
+					let profile = StripedProfile::new(&query, &similarity, open, extend);
+					let expected = profile.local_score_scalar(&db);
+					assert_eq!(profile.local_score(&db), expected, "len {} seed {} gaps {:?}", len, seed, (open, extend));
+					#[cfg(target_arch = "x86_64")]
+					{
+						if let Some(score) = profile.local_score_u8(&db) {
+							assert_eq!(score, expected);
+						}
+						assert_eq!(profile.local_score_i16(&db), Some(expected));
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_overflow_fallbacks() {
+		// an identical stretch scoring far more than fits in u8 lanes, and then in i16 lanes
+		let query = random_seq(b"ACGT", 300, 3);
+		let mut db = random_seq(b"ACGT", 100, 4);
+		db.extend(&query[20..280]);
+		db.extend(random_seq(b"ACGT", 100, 5));
+		let profile = StripedProfile::new(&query, &dna_similarity, -5, -2);
+		let expected = profile.local_score_scalar(&db);
+		assert!(expected >= 2 * 260);
+		assert_eq!(profile.local_score(&db), expected);
+		#[cfg(target_arch = "x86_64")]
+		{
+			assert_eq!(profile.local_score_u8(&db), None);
+			assert_eq!(profile.local_score_i16(&db), Some(expected));
+		}
+		let huge = |a: &u8, b: &u8| if a == b { 200 } else { -300 };
+		let profile = StripedProfile::new(&query, &huge, -500, -100);
+		let expected = profile.local_score_scalar(&db);
+		assert!(expected > i16::MAX as i64);
+		assert_eq!(profile.local_score(&db), expected);
+		assert_eq!(StripedProfile::new(&[], &huge, -5, -1).local_score(&db), 0);
+		assert_eq!(profile.local_score(&[]), 0);
+	}
+}
+
+#[cfg(test)]
+mod bench {
+	use test::Bencher;
+	use super::{StripedProfile, PROTEIN};
+	use test_support::random_seq;
+	use substitution_matrix::SubstitutionMatrix;
+
+	#[bench]
+	fn bench_scalar(b: &mut Bencher) {
+		let blosum62 = SubstitutionMatrix::blosum62();
+		let profile = StripedProfile::new(&random_seq(PROTEIN, 300, 1), &blosum62.similarity(), -11, -1);
+		let db = random_seq(PROTEIN, 3000, 2);
+		b.iter(|| profile.local_score_scalar(&db));
+	}
+
+	#[bench]
+	fn bench_striped(b: &mut Bencher) {
+		let blosum62 = SubstitutionMatrix::blosum62();
+		let profile = StripedProfile::new(&random_seq(PROTEIN, 300, 1), &blosum62.similarity(), -11, -1);
+		let db = random_seq(PROTEIN, 3000, 2);
+		b.iter(|| profile.local_score(&db));
+	}
+}
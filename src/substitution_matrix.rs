@@ -0,0 +1,265 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+const BLOSUM62: &str = include_str!("matrices/BLOSUM62");
+const PAM250: &str = include_str!("matrices/PAM250");
+
+#[derive(Debug)]
+pub enum MatrixError {
+	Io(io::Error),
+	// there is no line with the column symbols
+	MissingHeader,
+	// line number (starting at 1) and a description of the problem
+	Parse(usize, String),
+}
+
+impl fmt::Display for MatrixError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MatrixError::Io(ref e) => write!(f, "cannot read matrix: {}", e),
+			MatrixError::MissingHeader => write!(f, "matrix has no header line"),
+			MatrixError::Parse(line, ref msg) => write!(f, "line {}: {}", line, msg),
+		}
+	}
+}
+
+impl From<io::Error> for MatrixError {
+	fn from(e: io::Error) -> MatrixError {
+		MatrixError::Io(e)
+	}
+}
+
+/// A table of scores for pairs of single byte symbols, e.g. BLOSUM62 for amino acids.
+/// Lookups are case insensitive. Pairs involving a symbol missing from the table score
+/// `default`, which is the lowest score of the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstitutionMatrix {
+	alphabet: Vec<u8>,
+	scores: Vec<Option<i64>>,
+	default: i64,
+}
+
+impl SubstitutionMatrix {
+	/// Parse the NCBI text format: `#` comment lines, a header line with the column symbols,
+	/// then one line per row symbol followed by its scores. Every symbol of the header has
+	/// exactly one row.
+	pub fn parse(text: &str) -> Result<SubstitutionMatrix, MatrixError> {
+		let mut lines = text.lines()
+			.enumerate()
+			.map(|(i, l)| (i + 1, l.trim()))
+			.filter(|&(_, l)| !l.is_empty() && !l.starts_with('#'));
+		let (header_line, header) = lines.next().ok_or(MatrixError::MissingHeader)?;
+		let symbol = |line: usize, s: &str| -> Result<u8, MatrixError> {
+			match s.as_bytes() {
+				[c] if c.is_ascii() => Ok(c.to_ascii_uppercase()),
+				_ => Err(MatrixError::Parse(line, format!("'{}' is not a single ASCII symbol", s))),
+			}
+		};
+		let alphabet = header.split_whitespace()
+			.map(|s| symbol(header_line, s))
+			.collect::<Result<Vec<u8>, MatrixError>>()?;
+		for (i, &c) in alphabet.iter().enumerate() {
+			if alphabet[..i].contains(&c) {
+				return Err(MatrixError::Parse(header_line, format!("column '{}' appears twice", c as char)));
+			}
+		}
+		let mut matrix = SubstitutionMatrix {
+			alphabet: alphabet.clone(),
+			scores: vec![None; 128 * 128],
+			default: 0,
+		};
+		let mut rows: Vec<u8> = Vec::new();
+		for (line, l) in lines {
+			let mut tokens = l.split_whitespace();
+			let row = symbol(line, tokens.next().unwrap())?;
+			if !alphabet.contains(&row) {
+				return Err(MatrixError::Parse(line, format!("row '{}' is not in the header", row as char)));
+			}
+			if rows.contains(&row) {
+				return Err(MatrixError::Parse(line, format!("row '{}' appears twice", row as char)));
+			}
+			let scores = tokens
+				.map(|s| s.parse::<i64>().map_err(|_| MatrixError::Parse(line, format!("'{}' is not a score", s))))
+				.collect::<Result<Vec<i64>, MatrixError>>()?;
+			if scores.len() != alphabet.len() {
+				return Err(MatrixError::Parse(line, format!("expected {} scores, found {}", alphabet.len(), scores.len())));
+			}
+			for (&col, &score) in alphabet.iter().zip(scores.iter()) {
+				matrix.set(row, col, score);
+			}
+			rows.push(row);
+		}
+		if rows.is_empty() {
+			return Err(MatrixError::Parse(header_line, "matrix has no rows".to_string()));
+		}
+		if let Some(&c) = alphabet.iter().find(|c| !rows.contains(c)) {
+			return Err(MatrixError::Parse(header_line, format!("column '{}' has no row", c as char)));
+		}
+		matrix.default = matrix.scores.iter().filter_map(|s| *s).min().unwrap();
+		Ok(matrix)
+	}
+
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<SubstitutionMatrix, MatrixError> {
+		let mut text = String::new();
+		File::open(path)?.read_to_string(&mut text)?;
+		SubstitutionMatrix::parse(&text)
+	}
+
+	pub fn blosum62() -> SubstitutionMatrix {
+		SubstitutionMatrix::parse(BLOSUM62).unwrap()
+	}
+
+	pub fn pam250() -> SubstitutionMatrix {
+		SubstitutionMatrix::parse(PAM250).unwrap()
+	}
+
+	/// `match_score` on the diagonal and `mismatch_score` everywhere else.
+	pub fn identity(alphabet: &[u8], match_score: i64, mismatch_score: i64) -> SubstitutionMatrix {
+		let mut matrix = SubstitutionMatrix {
+			alphabet: alphabet.iter().map(|c| c.to_ascii_uppercase()).collect(),
+			scores: vec![None; 128 * 128],
+			default: match_score.min(mismatch_score),
+		};
+		for &a in alphabet {
+			for &b in alphabet {
+				matrix.set(a, b, if a.eq_ignore_ascii_case(&b) { match_score } else { mismatch_score });
+			}
+		}
+		matrix
+	}
+
+	/// +1 for identical nucleotides, -1 otherwise.
+	pub fn dna_identity() -> SubstitutionMatrix {
+		SubstitutionMatrix::identity(b"ACGT", 1, -1)
+	}
+
+	fn index(a: u8, b: u8) -> usize {
+		(a as usize) * 128 + b as usize
+	}
+
+	fn set(&mut self, a: u8, b: u8, score: i64) {
+		for &x in &[a.to_ascii_uppercase(), a.to_ascii_lowercase()] {
+			for &y in &[b.to_ascii_uppercase(), b.to_ascii_lowercase()] {
+				self.scores[SubstitutionMatrix::index(x, y)] = Some(score);
+			}
+		}
+	}
+
+	pub fn alphabet(&self) -> &[u8] {
+		&self.alphabet
+	}
+
+	/// The score from the table, None if either symbol is not in it.
+	pub fn get(&self, a: u8, b: u8) -> Option<i64> {
+		if a.is_ascii() && b.is_ascii() {
+			self.scores[SubstitutionMatrix::index(a, b)]
+		} else {
+			None
+		}
+	}
+
+	pub fn score(&self, a: u8, b: u8) -> i64 {
+		self.get(a, b).unwrap_or(self.default)
+	}
+
+	/// Adapter for the aligners taking `similarity: &dyn Fn(&u8, &u8) -> i64`.
+	pub fn similarity<'a>(&'a self) -> impl Fn(&u8, &u8) -> i64 + 'a {
+		move |a: &u8, b: &u8| self.score(*a, *b)
+	}
+
+	/// Adapter for aligning `char` sequences, non-ASCII characters score `default`.
+	pub fn char_similarity<'a>(&'a self) -> impl Fn(&char, &char) -> i64 + 'a {
+		move |a: &char, b: &char| {
+			if a.is_ascii() && b.is_ascii() {
+				self.score(*a as u8, *b as u8)
+			} else {
+				self.default
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{SubstitutionMatrix, MatrixError};
+	use needleman_wunsch::{NeedlemanWunsch, align_score};
+
+	#[test]
+	fn test_builtin_matrices() {
+		let blosum = SubstitutionMatrix::blosum62();
+		assert_eq!(blosum.alphabet().len(), 24);
+		assert_eq!(blosum.score(b'W', b'W'), 11);
+		assert_eq!(blosum.score(b'A', b'A'), 4);
+		assert_eq!(blosum.score(b'C', b'C'), 9);
+		assert_eq!(blosum.score(b'E', b'D'), 2);
+		assert_eq!(blosum.score(b'w', b'y'), 2);
+		assert_eq!(blosum.get(b'J', b'A'), None);
+		assert_eq!(blosum.score(b'J', b'A'), -4);
+		let pam = SubstitutionMatrix::pam250();
+		assert_eq!(pam.score(b'W', b'W'), 17);
+		assert_eq!(pam.score(b'C', b'C'), 12);
+		assert_eq!(pam.score(b'C', b'W'), -8);
+		for m in &[blosum, pam] {
+			for &a in m.alphabet() {
+				for &b in m.alphabet() {
+					assert_eq!(m.score(a, b), m.score(b, a));
+				}
+			}
+		}
+		let dna = SubstitutionMatrix::dna_identity();
+		assert_eq!(dna.score(b'a', b'A'), 1);
+		assert_eq!(dna.score(b'G', b'T'), -1);
+		assert_eq!(dna.score(b'N', b'T'), -1);
+	}
+
+	#[test]
+	fn test_parse() {
+		let m = SubstitutionMatrix::parse("# comment\n\n   a  b\na  2 -1\nb -1  3\n").unwrap();
+		assert_eq!(m.alphabet(), b"AB");
+		assert_eq!(m.score(b'B', b'b'), 3);
+		assert_eq!(m.get(b'A', b'B'), Some(-1));
+		match SubstitutionMatrix::parse("# only comments\n") {
+			Err(MatrixError::MissingHeader) => {},
+			other => panic!("unexpected {:?}", other),
+		}
+		match SubstitutionMatrix::parse("  A  B\nA 1 x\n") {
+			Err(MatrixError::Parse(2, _)) => {},
+			other => panic!("unexpected {:?}", other),
+		}
+		match SubstitutionMatrix::parse("  A  B\nA 1\n") {
+			Err(MatrixError::Parse(2, _)) => {},
+			other => panic!("unexpected {:?}", other),
+		}
+		match SubstitutionMatrix::parse(" AB C\n") {
+			Err(MatrixError::Parse(1, _)) => {},
+			other => panic!("unexpected {:?}", other),
+		}
+		// every header symbol has exactly one row, rows of other symbols are errors
+		for &(text, line) in &[
+			("  A  B\nA 1 0\nB 0 1\na 2 0\n", 4),
+			("  A  B\nA 1 0\nC 0 1\n", 3),
+			("  A  B\nA 1 0\n", 1),
+			("  A  a\nA 1 0\n", 1),
+		] {
+			match SubstitutionMatrix::parse(text) {
+				Err(MatrixError::Parse(l, _)) if l == line => {},
+				other => panic!("unexpected {:?} for {:?}", other, text),
+			}
+		}
+	}
+
+	#[test]
+	fn test_align_with_matrix() {
+		let blosum = SubstitutionMatrix::blosum62();
+		let vec1 = b"HEAGAWGHEE".to_vec();
+		let vec2 = b"PAWHEAE".to_vec();
+		let res = vec1.align(&vec2, &blosum.similarity(), -8);
+		assert_eq!(res.score, res.linear_score(&blosum.similarity(), -8));
+		assert_eq!(res.score, align_score(&vec1, &vec2, &blosum.similarity(), -8));
+		let chars1: Vec<char> = "HEAGAWGHEE".chars().collect();
+		let chars2: Vec<char> = "PAWHEAE".chars().collect();
+		assert_eq!(chars1.align(&chars2, &blosum.char_similarity(), -8).score, res.score);
+	}
+}
@@ -0,0 +1,29 @@
+//! Deterministic pseudo random data for the tests and benches: a 64 bit linear congruential
+//! generator with Knuth's MMIX constants, whose output is the top 31 bits of the state.
+
+pub(crate) struct Lcg {
+	state: u64,
+}
+
+impl Lcg {
+	pub(crate) fn new(seed: u64) -> Lcg {
+		Lcg { state: seed }
+	}
+
+	/// Pseudo random number in 0..n.
+	pub(crate) fn below(&mut self, n: u64) -> u64 {
+		self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+		(self.state >> 33) % n
+	}
+}
+
+/// Sequence of n elements drawn from the alphabet.
+pub(crate) fn random_seq<T: Copy>(alphabet: &[T], n: usize, seed: u64) -> Vec<T> {
+	let mut rng = Lcg::new(seed);
+	(0..n).map(|_| alphabet[rng.below(alphabet.len() as u64) as usize]).collect()
+}
+
+/// DNA sequence of n bases.
+pub(crate) fn random_dna(n: usize, seed: u64) -> Vec<u8> {
+	random_seq(b"ACGT", n, seed)
+}
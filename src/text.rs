@@ -0,0 +1,149 @@
+//! Alignment of strings, either char by char or by user-perceived characters. The grapheme
+//! clusters follow the rules of UAX #29 for line breaks (CR LF), Hangul syllables (GB6 to
+//! GB8), extending chars (GB9), ZWJ sequences (GB11, after any char) and flags of regional
+//! indicator pairs (GB12, GB13). Without the Unicode data tables the Extend property is
+//! limited to the common combining mark blocks, variation selectors and emoji modifiers, and
+//! prepend and spacing marks are not joined.
+use alignment::Alignment;
+use needleman_wunsch::NeedlemanWunsch;
+
+/// Grapheme cluster break property of a char, as far as the rules here need it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Break {
+	CR,
+	LF,
+	Extend,
+	Zwj,
+	RegionalIndicator,
+	// Hangul leading consonant, vowel and trailing consonant jamo, and precomposed syllables
+	// without and with trailing consonant
+	L,
+	V,
+	T,
+	Lv,
+	Lvt,
+	Other,
+}
+
+fn break_property(c: char) -> Break {
+	match c {
+		'\r' => Break::CR,
+		'\n' => Break::LF,
+		'\u{200D}' => Break::Zwj,
+		'\u{0300}'..='\u{036F}' // combining diacritical marks
+		| '\u{1AB0}'..='\u{1AFF}'
+		| '\u{1DC0}'..='\u{1DFF}'
+		| '\u{20D0}'..='\u{20FF}' // combining marks for symbols
+		| '\u{FE20}'..='\u{FE2F}' // combining half marks
+		| '\u{FE00}'..='\u{FE0F}' // variation selectors
+		| '\u{1F3FB}'..='\u{1F3FF}' => Break::Extend, // emoji skin tone modifiers
+		'\u{1F1E6}'..='\u{1F1FF}' => Break::RegionalIndicator,
+		'\u{1100}'..='\u{115F}' | '\u{A960}'..='\u{A97C}' => Break::L,
+		'\u{1160}'..='\u{11A7}' | '\u{D7B0}'..='\u{D7C6}' => Break::V,
+		'\u{11A8}'..='\u{11FF}' | '\u{D7CB}'..='\u{D7FB}' => Break::T,
+		// every 28th precomposed syllable has no trailing consonant
+		'\u{AC00}'..='\u{D7A3}' if (c as u32 - 0xAC00).is_multiple_of(28) => Break::Lv,
+		'\u{AC00}'..='\u{D7A3}' => Break::Lvt,
+		_ => Break::Other,
+	}
+}
+
+/// Split a string into extended grapheme clusters.
+pub fn graphemes(s: &str) -> Vec<&str> {
+	let mut res = Vec::new();
+	let mut start = 0;
+	let mut prev: Option<Break> = None;
+	// regional indicators in a row just before the current char
+	let mut indicators = 0;
+	for (i, c) in s.char_indices() {
+		let cur = break_property(c);
+		let joined = match (prev, cur) {
+			(None, _) => true,
+			(Some(Break::CR), Break::LF) => true,
+			(Some(Break::CR), _) | (Some(Break::LF), _) | (_, Break::CR) | (_, Break::LF) => false,
+			(Some(Break::L), Break::L) | (Some(Break::L), Break::V) | (Some(Break::L), Break::Lv) | (Some(Break::L), Break::Lvt) => true,
+			(Some(Break::Lv), Break::V) | (Some(Break::Lv), Break::T) | (Some(Break::V), Break::V) | (Some(Break::V), Break::T) => true,
+			(Some(Break::Lvt), Break::T) | (Some(Break::T), Break::T) => true,
+			(_, Break::Extend) | (_, Break::Zwj) | (Some(Break::Zwj), _) => true,
+			// a flag is a pair, the third indicator in a row starts the next one
+			(Some(Break::RegionalIndicator), Break::RegionalIndicator) => indicators % 2 == 1,
+			_ => false,
+		};
+		if !joined {
+			res.push(&s[start..i]);
+			start = i;
+		}
+		indicators = if cur == Break::RegionalIndicator { indicators + 1 } else { 0 };
+		prev = Some(cur);
+	}
+	if start < s.len() {
+		res.push(&s[start..]);
+	}
+	res
+}
+
+/// Global alignment of the chars of two strings.
+pub fn align_chars(a: &str, b: &str, similarity: &dyn Fn(&char, &char) -> i64, gap_penalty: i64) -> Alignment<char> {
+	let chars1: Vec<char> = a.chars().collect();
+	let chars2: Vec<char> = b.chars().collect();
+	chars1.align(&chars2, similarity, gap_penalty)
+}
+
+/// Global alignment of the grapheme clusters of two strings, the columns borrow from them.
+pub fn align_graphemes<'a>(a: &'a str, b: &'a str, similarity: &dyn Fn(&&str, &&str) -> i64, gap_penalty: i64) -> Alignment<&'a str> {
+	graphemes(a).align(&graphemes(b), similarity, gap_penalty)
+}
+
+#[cfg(test)]
+mod test {
+	use super::{graphemes, align_chars, align_graphemes};
+
+	#[test]
+	fn test_graphemes() {
+		assert_eq!(graphemes("abc"), vec!["a", "b", "c"]);
+		assert_eq!(graphemes(""), Vec::<&str>::new());
+		// e followed by a combining acute accent
+		assert_eq!(graphemes("cafe\u{301}!"), vec!["c", "a", "f", "e\u{301}", "!"]);
+		assert_eq!(graphemes("a\r\nb\n\r"), vec!["a", "\r\n", "b", "\n", "\r"]);
+		// thumbs up with a skin tone, family joined by zero width joiners
+		assert_eq!(graphemes("\u{1F44D}\u{1F3FD}x"), vec!["\u{1F44D}\u{1F3FD}", "x"]);
+		assert_eq!(graphemes("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}."), vec!["\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}", "."]);
+		// a leading mark has nothing to attach to and stands alone
+		assert_eq!(graphemes("\u{301}a"), vec!["\u{301}", "a"]);
+		// flags: regional indicators pair up from the start of the run
+		assert_eq!(graphemes("\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}"), vec!["\u{1F1E9}\u{1F1EA}", "\u{1F1EB}\u{1F1F7}"]);
+		assert_eq!(graphemes("\u{1F1E9}\u{1F1EA}\u{1F1EB}x"), vec!["\u{1F1E9}\u{1F1EA}", "\u{1F1EB}", "x"]);
+		// Hangul: decomposed jamo L V T, a precomposed LV syllable with a trailing T, and a
+		// precomposed LVT syllable which takes no further vowel
+		assert_eq!(graphemes("\u{1100}\u{1161}\u{11A8}\u{1100}\u{1161}"), vec!["\u{1100}\u{1161}\u{11A8}", "\u{1100}\u{1161}"]);
+		assert_eq!(graphemes("\u{AC00}\u{11A8}a"), vec!["\u{AC00}\u{11A8}", "a"]);
+		assert_eq!(graphemes("\u{AC01}\u{1161}"), vec!["\u{AC01}", "\u{1161}"]);
+		assert_eq!(graphemes("\u{1100}\u{1100}\u{AC00}"), vec!["\u{1100}\u{1100}\u{AC00}"]);
+		// a CR LF pair does not take marks
+		assert_eq!(graphemes("\r\n\u{301}"), vec!["\r\n", "\u{301}"]);
+	}
+
+	#[test]
+	fn test_align_chars() {
+		let res = align_chars("what", "white", &|a, b| if a == b { 1 } else { -1 }, -1);
+		assert_eq!(res.score, 1);
+		assert_eq!(format!("{}", res), "what-\n|| |\nwhite");
+		assert_eq!(align_chars("naïve", "naive", &|a, b| if a == b { 1 } else { -1 }, -1).score, 3);
+	}
+
+	#[test]
+	fn test_align_graphemes() {
+		let similarity = |a: &&str, b: &&str| if a == b { 1 } else { -1 };
+		// by char the decomposed é is two elements, by grapheme one
+		let res = align_graphemes("cafe\u{301}", "cafe", &similarity, -1);
+		assert_eq!(res.len(), 4);
+		assert_eq!(res.score, 2);
+		assert_eq!(res.seq1(), vec![Some("c"), Some("a"), Some("f"), Some("e\u{301}")]);
+		assert_eq!(align_chars("cafe\u{301}", "cafe", &|a, b| if a == b { 1 } else { -1 }, -1).len(), 5);
+		let res = align_graphemes("\u{1F44D}\u{1F3FD}ok", "ok", &similarity, -1);
+		assert_eq!(res.seq2(), vec![None, Some("o"), Some("k")]);
+		// two flags against one of them: one gap, not one per indicator
+		let res = align_graphemes("\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}", "\u{1F1EB}\u{1F1F7}", &similarity, -1);
+		assert_eq!(res.seq2(), vec![None, Some("\u{1F1EB}\u{1F1F7}")]);
+	}
+}
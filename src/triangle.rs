@@ -1,15 +1,17 @@
-
-use super::point::Point;
+use super::point::{Point, Direction};
+use super::coordinate::Coordinate;
+use super::definite_num::DefinitelyANumber;
+use super::rect::Rect;
 
 #[derive(PartialEq, Eq, Debug)]
-pub struct Triangle {
-    pub p0: Point,
-    pub p1: Point,
-    pub p2: Point,
+pub struct Triangle<T = DefinitelyANumber> {
+    pub p0: Point<T>,
+    pub p1: Point<T>,
+    pub p2: Point<T>,
 }
 
-impl Triangle {
-    pub fn new(p0: Point, p1: Point, p2: Point) -> Triangle {
+impl<T> Triangle<T> where T: Coordinate {
+    pub fn new(p0: Point<T>, p1: Point<T>, p2: Point<T>) -> Triangle<T> {
         // Sort by x-coordinate to make sure the first point is the leftmost and lowest.
         let mut v = [p0, p1, p2];
         v.sort();
@@ -30,21 +32,20 @@ impl Triangle {
         (v[0].y.to_f64(), v[2].y.to_f64())
     }
 
-    // Barycentric Technique, check whether point is in triangle, see http://blackpawn.com/texts/pointinpoly/
-    pub fn contains(&self, p: Point) -> bool {
-        let v0 = self.p2 - self.p0;
-        let v1 = self.p1 - self.p0;
-        let v2 = p - self.p0;
-        let dot00 = v0 * v0;
-        let dot01 = v0 * v1;
-        let dot02 = v0 * v2;
-        let dot11 = v1 * v1;
-        let dot12 = v1 * v2;
-        let inv_denom = (dot00 * dot11 - dot01 * dot01).recip();
-        let u = (dot11 * dot02 - dot01 * dot12) * inv_denom;
-        let v = (dot00 * dot12 - dot01 * dot02) * inv_denom;
+    // bounding box, the exact counterpart of range_x and range_y
+    pub fn bounds(&self) -> Rect<T> {
+        let min_y = self.p0.y.min(self.p1.y).min(self.p2.y);
+        let max_y = self.p0.y.max(self.p1.y).max(self.p2.y);
+        Rect::new(Point::from_coords(self.p0.x, min_y), Point::from_coords(self.p2.x, max_y))
+    }
 
-        (u > 0.0) && (v > 0.0) && (u + v < 1.0)
+    // The point is strictly inside when it lies on the same side of all three edges.
+    // Only orientation tests are used, so this is exact for integer and rational coordinates.
+    pub fn contains(&self, p: Point<T>) -> bool {
+        let d0 = self.p0.direction(&self.p1, &p);
+        let d1 = self.p1.direction(&self.p2, &p);
+        let d2 = self.p2.direction(&self.p0, &p);
+        d0 != Direction::Ahead && d0 == d1 && d1 == d2
     }
 }
 
@@ -53,6 +54,7 @@ impl Triangle {
 mod test {
 	use point::Point; 
 	use triangle::Triangle;
+	use rect::Rect;
 	#[test]
     fn test_triangle() {
         let p2 = Point::new(0.0, 0.0);
@@ -93,4 +95,20 @@ mod test {
         assert_eq!(t.p2, p0);
     }
 
+	#[test]
+    fn test_integer_triangle() {
+        let t = Triangle::new(Point::from_coords(0i64, 0), Point::from_coords(4, 0), Point::from_coords(0, 4));
+        assert_eq!(t.range_x(), (0.0, 4.0));
+        assert_eq!(t.range_y(), (0.0, 4.0));
+        assert!(t.contains(Point::from_coords(1, 1)));
+        assert!(t.contains(Point::from_coords(1, 2)));
+        // on the hypotenuse
+        assert!(!t.contains(Point::from_coords(2, 2)));
+        assert!(!t.contains(Point::from_coords(3, 3)));
+        assert_eq!(t.bounds(), Rect::new(Point::from_coords(0, 0), Point::from_coords(4, 4)));
+        // degenerate triangles contain nothing
+        let t = Triangle::new(Point::from_coords(0i32, 0), Point::from_coords(1, 1), Point::from_coords(2, 2));
+        assert!(!t.contains(Point::from_coords(1, 1)));
+    }
+
 }
@@ -0,0 +1,225 @@
+//! Parallel fill of the Needleman-Wunsch matrix in anti-diagonal wavefronts. The matrix is
+//! cut into square tiles; a tile only needs the last row of the tile above and the last
+//! column of the tile to its left, so all tiles on one anti-diagonal of tiles are independent
+//! and are filled by worker threads at the same time. Every cell is computed exactly like the
+//! sequential fill, so the matrix and score are the same.
+use std::thread;
+
+use alignment::Alignment;
+use needleman_wunsch::{AlignMatrix, AlignMode, ResultEntry, TieBreak, best_entry, init_align_matrix, traceback};
+
+/// Side length of a tile. Large enough that spawning the workers of a wavefront is cheap
+/// compared to filling its tiles.
+const TILE: usize = 256;
+
+/// One tile: its top left cell is (r0, c0), which belongs to the tiles above and to the
+/// left, the tile itself covers rows r0 + 1 ..= r0 + h and columns c0 + 1 ..= c0 + w.
+#[derive(Debug, Copy, Clone)]
+struct Tile {
+	r0: usize,
+	c0: usize,
+	h: usize,
+	w: usize,
+}
+
+/// The tiles of every anti-diagonal, first to last.
+fn wavefronts(len1: usize, len2: usize) -> Vec<Vec<(usize, usize, Tile)>> {
+	let tiles1 = len1.div_ceil(TILE);
+	let tiles2 = len2.div_ceil(TILE);
+	if tiles1 == 0 || tiles2 == 0 {
+		return Vec::new();
+	}
+	(0..(tiles1 + tiles2 - 1)).map(|d| {
+		(0..tiles1).filter(|&bi| bi <= d && d - bi < tiles2).map(|bi| {
+			let bj = d - bi;
+			let (r0, c0) = (bi * TILE, bj * TILE);
+			(bi, bj, Tile { r0, c0, h: TILE.min(len1 - r0), w: TILE.min(len2 - c0) })
+		}).collect()
+	}).collect()
+}
+
+/// Run `f` on every item, spread over at most `threads` scoped workers, results in order.
+fn run_parallel<X: Sync, R: Send>(items: &[X], threads: usize, f: &(dyn Fn(&X) -> R + Sync)) -> Vec<R> {
+	if threads <= 1 || items.len() <= 1 {
+		return items.iter().map(f).collect();
+	}
+	let chunk = items.len().div_ceil(threads);
+	thread::scope(|s| {
+		let workers: Vec<_> = items.chunks(chunk).map(|c| s.spawn(move || c.iter().map(f).collect::<Vec<R>>())).collect();
+		workers.into_iter().flat_map(|w| w.join().unwrap()).collect()
+	})
+}
+
+/// Fill one tile from the scores on its top row (`top`, columns c0 ..= c0 + w) and left
+/// column (`left`, rows r0 ..= r0 + h). Returns the entries row by row.
+fn fill_tile<T>(tile: Tile, (top, left): (&[i64], &[i64]), vec1: &[T], vec2: &[T], similarity: &(dyn Fn(&T, &T) -> i64 + Sync), gap_penalty: i64, tie_break: TieBreak) -> Vec<ResultEntry> {
+	let mut entries = Vec::with_capacity(tile.h * tile.w);
+	let mut prev = top.to_vec();
+	let mut cur = vec![0; tile.w + 1];
+	for a in 0..tile.h {
+		let x = &vec1[tile.r0 + a];
+		cur[0] = left[a + 1];
+		for b in 0..tile.w {
+			let match_score = prev[b] + similarity(x, &vec2[tile.c0 + b]);
+			let entry = best_entry(match_score, prev[b + 1] + gap_penalty, cur[b] + gap_penalty, tie_break);
+			cur[b + 1] = entry.score;
+			entries.push(entry);
+		}
+		::std::mem::swap(&mut prev, &mut cur);
+	}
+	entries
+}
+
+/// Scores only version of `fill_tile`, returns the last row and the last column of the tile
+/// including the corners they share with the tiles to the left and above.
+fn score_tile<T>(tile: Tile, (top, left): (&[i64], &[i64]), vec1: &[T], vec2: &[T], similarity: &(dyn Fn(&T, &T) -> i64 + Sync), gap_penalty: i64) -> (Vec<i64>, Vec<i64>) {
+	let mut prev = top.to_vec();
+	let mut cur = vec![0; tile.w + 1];
+	let mut right = Vec::with_capacity(tile.h + 1);
+	right.push(top[tile.w]);
+	for a in 0..tile.h {
+		let x = &vec1[tile.r0 + a];
+		cur[0] = left[a + 1];
+		for b in 0..tile.w {
+			let match_score = prev[b] + similarity(x, &vec2[tile.c0 + b]);
+			cur[b + 1] = match_score.max(prev[b + 1] + gap_penalty).max(cur[b] + gap_penalty);
+		}
+		right.push(cur[tile.w]);
+		::std::mem::swap(&mut prev, &mut cur);
+	}
+	(prev, right)
+}
+
+/// Parallel version of `fill_align_matrix` with the given number of worker threads.
+pub(crate) fn fill_align_matrix_parallel<T: Sync>(mat: &mut AlignMatrix, vec1: &[T], vec2: &[T], similarity: &(dyn Fn(&T, &T) -> i64 + Sync), gap_penalty: i64, tie_break: TieBreak, threads: usize) {
+	assert_eq!(mat.len(), vec1.len() + 1);
+	assert_eq!(mat[0].len(), vec2.len() + 1);
+	for wavefront in wavefronts(vec1.len(), vec2.len()) {
+		let filled = {
+			let mat: &AlignMatrix = mat;
+			run_parallel(&wavefront, threads, &|&(_, _, tile)| {
+				let top: Vec<i64> = mat[tile.r0][tile.c0..(tile.c0 + tile.w + 1)].iter().map(|e| e.score).collect();
+				let left: Vec<i64> = mat[tile.r0..(tile.r0 + tile.h + 1)].iter().map(|row| row[tile.c0].score).collect();
+				fill_tile(tile, (&top, &left), vec1, vec2, similarity, gap_penalty, tie_break)
+			})
+		};
+		for (&(_, _, tile), entries) in wavefront.iter().zip(filled) {
+			for (a, row) in entries.chunks(tile.w).enumerate() {
+				mat[tile.r0 + a + 1][(tile.c0 + 1)..(tile.c0 + tile.w + 1)].copy_from_slice(row);
+			}
+		}
+	}
+}
+
+/// Global alignment like `NeedlemanWunsch::align`, with the matrix filled by `threads` workers.
+pub fn align_parallel<T>(vec1: &[T], vec2: &[T], similarity: &(dyn Fn(&T, &T) -> i64 + Sync), gap_penalty: i64, threads: usize) -> Alignment<T>
+	where T: Eq + Copy + Sync {
+	let mut mat = init_align_matrix(vec1.len(), vec2.len(), gap_penalty, AlignMode::Global);
+	fill_align_matrix_parallel(&mut mat, vec1, vec2, similarity, gap_penalty, TieBreak::default(), threads);
+	let (seq1, seq2, _) = traceback(&mat, vec1, vec2, vec1.len(), vec2.len());
+	Alignment::from_pair(seq1, seq2, mat[vec1.len()][vec2.len()].score)
+}
+
+/// Score of the optimal global alignment, like `align_score`, filled by `threads` workers.
+/// Only the last row and column of every tile are kept, so inputs far too large for the full
+/// matrix work.
+pub fn align_score_parallel<T: Sync>(vec1: &[T], vec2: &[T], similarity: &(dyn Fn(&T, &T) -> i64 + Sync), gap_penalty: i64, threads: usize) -> i64 {
+	let (len1, len2) = (vec1.len(), vec2.len());
+	if len1 == 0 || len2 == 0 {
+		return (len1 + len2) as i64 * gap_penalty;
+	}
+	// the last row of the latest tile in every tile column and the last column of the latest
+	// tile in every tile row, both including the shared corner
+	let mut rows: Vec<Vec<i64>> = (0..len2.div_ceil(TILE)).map(|bj| {
+		let c0 = bj * TILE;
+		(c0..(c0 + TILE.min(len2 - c0) + 1)).map(|j| j as i64 * gap_penalty).collect()
+	}).collect();
+	let mut cols: Vec<Vec<i64>> = (0..len1.div_ceil(TILE)).map(|bi| {
+		let r0 = bi * TILE;
+		(r0..(r0 + TILE.min(len1 - r0) + 1)).map(|i| i as i64 * gap_penalty).collect()
+	}).collect();
+	for wavefront in wavefronts(len1, len2) {
+		let edges = {
+			let (rows, cols) = (&rows, &cols);
+			run_parallel(&wavefront, threads, &|&(bi, bj, tile)| {
+				score_tile(tile, (&rows[bj], &cols[bi]), vec1, vec2, similarity, gap_penalty)
+			})
+		};
+		for (&(bi, bj, _), (bottom, right)) in wavefront.iter().zip(edges) {
+			rows[bj] = bottom;
+			cols[bi] = right;
+		}
+	}
+	*rows.last().unwrap().last().unwrap()
+}
+
+#[cfg(test)]
+fn dna_similarity(a: &u8, b: &u8) -> i64 {
+	if a == b { 2 } else { -1 }
+}
+
+#[cfg(test)]
+mod test {
+	use super::{fill_align_matrix_parallel, align_parallel, align_score_parallel, dna_similarity};
+	use test_support::random_dna;
+	use needleman_wunsch::{AlignMode, NeedlemanWunsch, TieBreak, align_score, fill_align_matrix, init_align_matrix};
+
+	#[test]
+	fn test_parallel_fill_matches_sequential() {
+		// several tiles in both directions, partial tiles at the edges
+		for &(len1, len2) in &[(600, 530), (1, 700), (300, 1), (256, 512)] {
+			let vec1 = random_dna(len1, len1 as u64);
+			let vec2 = random_dna(len2, len2 as u64 + 7);
+			let mut sequential = init_align_matrix(len1, len2, -2, AlignMode::Global);
+			fill_align_matrix(&mut sequential, &vec1, &vec2, &dna_similarity, -2, TieBreak::default());
+			for &threads in &[1, 3] {
+				let mut parallel = init_align_matrix(len1, len2, -2, AlignMode::Global);
+				fill_align_matrix_parallel(&mut parallel, &vec1, &vec2, &dna_similarity, -2, TieBreak::default(), threads);
+				assert!(parallel == sequential, "{} x {} with {} threads", len1, len2, threads);
+				assert_eq!(align_score_parallel(&vec1, &vec2, &dna_similarity, -2, threads), sequential[len1][len2].score);
+			}
+		}
+	}
+
+	#[test]
+	fn test_align_parallel() {
+		let vec1 = random_dna(700, 1);
+		let vec2 = random_dna(650, 2);
+		let parallel = align_parallel(&vec1, &vec2, &dna_similarity, -2, 4);
+		assert_eq!(parallel, vec1.align(&vec2, &dna_similarity, -2));
+		assert_eq!(align_score_parallel(&vec1, &[], &dna_similarity, -2, 4), align_score(&vec1, &[], &dna_similarity, -2));
+		assert_eq!(align_parallel::<u8>(&[], &[], &dna_similarity, -2, 4).len(), 0);
+	}
+}
+
+#[cfg(test)]
+mod bench {
+	use std::thread;
+	use test::Bencher;
+	use super::{align_score_parallel, dna_similarity};
+	use test_support::random_dna;
+	use needleman_wunsch::align_score;
+
+	// a single 20k x 20k iteration takes seconds, so these only run when asked for with
+	// `cargo bench wavefront -- --ignored`
+	const LEN: usize = 20_000;
+
+	fn threads() -> usize {
+		thread::available_parallelism().map_or(1, |n| n.get())
+	}
+
+	#[bench]
+	#[ignore]
+	fn bench_score_sequential(b: &mut Bencher) {
+		let (vec1, vec2) = (random_dna(LEN, 1), random_dna(LEN, 2));
+		b.iter(|| align_score(&vec1, &vec2, &dna_similarity, -2));
+	}
+
+	#[bench]
+	#[ignore]
+	fn bench_score_parallel(b: &mut Bencher) {
+		let (vec1, vec2) = (random_dna(LEN, 1), random_dna(LEN, 2));
+		let threads = threads();
+		b.iter(|| align_score_parallel(&vec1, &vec2, &dna_similarity, -2, threads));
+	}
+}